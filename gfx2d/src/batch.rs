@@ -48,6 +48,12 @@ impl<'a> DrawSlice<'a> {
     }
 }
 
+impl Default for DrawBatch {
+    fn default() -> DrawBatch {
+        Self::new()
+    }
+}
+
 impl DrawBatch {
     pub fn new() -> DrawBatch {
         Self::with_usage(BatchUsage::Dynamic)
@@ -99,7 +105,7 @@ impl DrawBatch {
     fn last_texture(&self) -> Option<&Texture> {
         match self.cmds.last() {
             None => None,
-            Some(ref cmd) => cmd.texture.as_ref().clone()
+            Some(ref cmd) => cmd.texture.as_ref()
         }
     }
 
@@ -120,8 +126,8 @@ impl DrawBatch {
         let (ty0, ty1) = sprite.texcoords_y;
         let (a, b, c, d) = (vec2(0.0, 0.0), vec2(w, 0.0), vec2(w, h), vec2(0.0, h));
 
-        let (a, b, c, d) = match &transform {
-            &Transform::Pos(p) => (p + a, p + b, p + c, p + d),
+        let (a, b, c, d) = match transform {
+            Transform::Pos(p) => (p + a, p + b, p + c, p + d),
             _ => {
                 let m = transform.matrix();
                 (m*a, m*b, m*c, m*d)
@@ -12,10 +12,10 @@ pub fn rgba(r: u8, g: u8, b: u8, a: u8) -> Color {
 }
 
 impl Color {
-    pub fn r(&self) -> u8 { self.0[0].0 }
-    pub fn g(&self) -> u8 { self.0[1].0 }
-    pub fn b(&self) -> u8 { self.0[2].0 }
-    pub fn a(&self) -> u8 { self.0[3].0 }
+    pub fn r(self) -> u8 { self.0[0].0 }
+    pub fn g(self) -> u8 { self.0[1].0 }
+    pub fn b(self) -> u8 { self.0[2].0 }
+    pub fn a(self) -> u8 { self.0[3].0 }
     pub fn set_r(&mut self, value: u8) { self.0[0].0 = value; }
     pub fn set_g(&mut self, value: u8) { self.0[1].0 = value; }
     pub fn set_b(&mut self, value: u8) { self.0[2].0 = value; }
@@ -1,5 +1,6 @@
 #![crate_type = "lib"]
 #![crate_name = "gfx2d"]
+#![allow(clippy::many_single_char_names)]
 
 #[macro_use]
 extern crate gfx;
@@ -48,8 +48,8 @@ impl Sprite {
         let (w, h) = texture.dimensions();
 
         Sprite {
-            width: w as f32 / pixel_ratio.x,
-            height: h as f32 / pixel_ratio.y,
+            width: f32::from(w) / pixel_ratio.x,
+            height: f32::from(h) / pixel_ratio.y,
             texcoords_x: (0.0, 1.0),
             texcoords_y: (0.0, 1.0),
             texture: Some(texture.clone()),
@@ -135,8 +135,8 @@ impl Spritesheet {
             let (y0, y1) = (rc.top() as f32, (rc.bottom() - padding) as f32);
 
             sprite.texture = Some(texture.clone());
-            sprite.texcoords_x = (x0 / w as f32, x1 / w as f32);
-            sprite.texcoords_y = (y0 / h as f32, y1 / h as f32);
+            sprite.texcoords_x = (x0 / f32::from(w), x1 / f32::from(w));
+            sprite.texcoords_y = (y0 / f32::from(h), y1 / f32::from(h));
         }
 
         Spritesheet{textures, sprites}
@@ -173,7 +173,7 @@ impl Spritesheet {
                 let mut a = 0;
 
                 while a < area && i < rects.len() - 1 {
-                    a = a + (rects[i].w*rects[i].h).abs() as u64;
+                    a += (rects[i].w*rects[i].h).abs() as u64;
                     i += 1;
                 }
 
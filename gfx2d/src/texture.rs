@@ -66,12 +66,12 @@ pub fn load_image_rgba<P: AsRef<Path>>(filename: P) -> image::RgbaImage {
 
 pub fn premultiply_image(img: &mut image::RgbaImage) {
     for pixel in img.pixels_mut() {
-        let a = pixel[3] as f32 / 255.0;
+        let a = f32::from(pixel[3]) / 255.0;
 
         *pixel = image::Rgba([
-            (pixel[0] as f32 * a) as u8,
-            (pixel[1] as f32 * a) as u8,
-            (pixel[2] as f32 * a) as u8,
+            (f32::from(pixel[0]) * a) as u8,
+            (f32::from(pixel[1]) * a) as u8,
+            (f32::from(pixel[2]) * a) as u8,
             pixel[3],
         ]);
     }
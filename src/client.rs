@@ -0,0 +1,14 @@
+use na::Vector2;
+
+/// What the local player looks at, which has no effect on the simulation.
+pub struct ClientState {
+    /// The soldier being played.
+    pub player: usize,
+    pub camera: Vector2<f32>,
+    pub camera_prev: Vector2<f32>,
+    pub mouse: Vector2<f32>,
+    pub mouse_prev: Vector2<f32>,
+    pub game_width: f32,
+    pub game_height: f32,
+    pub zoom: f32,
+}
@@ -0,0 +1,131 @@
+use glutin::{VirtualKeyCode, MouseButton};
+use ini::Ini;
+use shared::control::Action;
+use shared::input::InputFrame;
+
+const SECTION: &str = "CONTROLS";
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Binding {
+    Key(VirtualKeyCode),
+    Mouse(MouseButton),
+}
+
+macro_rules! key_names {
+    ($($key:ident)+) => {
+        fn key_from_name(name: &str) -> Option<VirtualKeyCode> {
+            match name {
+                $(stringify!($key) => Some(VirtualKeyCode::$key),)+
+                _ => None,
+            }
+        }
+    }
+}
+
+key_names! {
+    A B C D E F G H I J K L M N O P Q R S T U V W X Y Z
+    Key1 Key2 Key3 Key4 Key5 Key6 Key7 Key8 Key9 Key0
+    F1 F2 F3 F4 F5 F6 F7 F8 F9 F10 F11 F12
+    Numpad0 Numpad1 Numpad2 Numpad3 Numpad4 Numpad5 Numpad6 Numpad7 Numpad8 Numpad9
+    Escape Insert Home Delete End PageDown PageUp Left Up Right Down Back Return Space Tab
+    Add Subtract Multiply Divide Decimal Apostrophe Backslash Comma Equals Grave Minus
+    Period Semicolon Slash LBracket RBracket Capital
+    LAlt LControl LShift RAlt RControl RShift
+}
+
+impl Binding {
+    pub fn from_name(name: &str) -> Option<Binding> {
+        match name {
+            "MouseLeft" => Some(Binding::Mouse(MouseButton::Left)),
+            "MouseRight" => Some(Binding::Mouse(MouseButton::Right)),
+            "MouseMiddle" => Some(Binding::Mouse(MouseButton::Middle)),
+            _ if name.starts_with("Mouse") => {
+                name[5..].parse().ok().map(|n| Binding::Mouse(MouseButton::Other(n)))
+            },
+            _ => key_from_name(name).map(Binding::Key),
+        }
+    }
+}
+
+pub struct KeyBindings {
+    bindings: Vec<(Binding, Action)>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> KeyBindings {
+        let mut bindings = KeyBindings { bindings: Vec::new() };
+
+        // Soldat defaults
+        bindings.bind(Action::Left,         Binding::Key(VirtualKeyCode::A));
+        bindings.bind(Action::Right,        Binding::Key(VirtualKeyCode::D));
+        bindings.bind(Action::Jump,         Binding::Key(VirtualKeyCode::W));
+        bindings.bind(Action::Crouch,       Binding::Key(VirtualKeyCode::S));
+        bindings.bind(Action::Prone,        Binding::Key(VirtualKeyCode::X));
+        bindings.bind(Action::Fire,         Binding::Mouse(MouseButton::Left));
+        bindings.bind(Action::Jet,          Binding::Mouse(MouseButton::Right));
+        bindings.bind(Action::Grenade,      Binding::Key(VirtualKeyCode::E));
+        bindings.bind(Action::ChangeWeapon, Binding::Key(VirtualKeyCode::Q));
+        bindings.bind(Action::ThrowWeapon,  Binding::Key(VirtualKeyCode::F));
+        bindings.bind(Action::Reload,       Binding::Key(VirtualKeyCode::R));
+        bindings.bind(Action::ThrowFlag,    Binding::Key(VirtualKeyCode::C));
+
+        bindings
+    }
+}
+
+impl KeyBindings {
+    /// Loads bindings from the `[CONTROLS]` section of an ini file. Each key is an action name
+    /// and its value a comma separated list of inputs, e.g. `Fire=MouseLeft,LControl`. Actions
+    /// missing from the file keep their default bindings.
+    pub fn load_from_file(path: &str) -> KeyBindings {
+        let mut bindings = KeyBindings::default();
+
+        if let Ok(cfg) = Ini::load_from_file(path) {
+            if let Some(data) = cfg.section(Some(SECTION.to_owned())) {
+                for (key, value) in data.iter() {
+                    let action = match Action::from_name(key) {
+                        Some(action) => action,
+                        None => { println!("Unknown control action: {}", key); continue; },
+                    };
+
+                    bindings.unbind_action(action);
+
+                    for name in value.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+                        match Binding::from_name(name) {
+                            Some(binding) => bindings.bind(action, binding),
+                            None => println!("Unknown input '{}' for control {}", name, key),
+                        }
+                    }
+                }
+            }
+        }
+
+        bindings
+    }
+
+    /// Binds an input to an action, replacing whatever the input was bound to before.
+    pub fn bind(&mut self, action: Action, binding: Binding) {
+        self.unbind(binding);
+        self.bindings.push((binding, action));
+    }
+
+    pub fn unbind(&mut self, binding: Binding) {
+        self.bindings.retain(|&(b, _)| b != binding);
+    }
+
+    pub fn unbind_action(&mut self, action: Action) {
+        self.bindings.retain(|&(_, a)| a != action);
+    }
+
+    pub fn action(&self, binding: Binding) -> Option<Action> {
+        self.bindings.iter().find(|&&(b, _)| b == binding).map(|&(_, action)| action)
+    }
+
+    /// Updates the input frame for the given input. Returns false if the input isn't bound.
+    pub fn apply(&self, input: &mut InputFrame, binding: Binding, pressed: bool) -> bool {
+        match self.action(binding) {
+            Some(action) => { input.set(action, pressed); true },
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,12 @@
+// The simulation follows Soldat's code closely, long functions, nested conditions and short names
+// included
+#![allow(clippy::cyclomatic_complexity, clippy::collapsible_if, clippy::many_single_char_names)]
+
+extern crate byteorder;
+extern crate nalgebra as na;
+extern crate ini;
+extern crate rand;
+
+// The simulation, shared by the game and the dedicated server
+#[macro_use]
+pub mod shared;
@@ -1,110 +1,75 @@
+// Like the simulation, the rendering code sticks to Soldat's long functions and short names
+#![allow(clippy::cyclomatic_complexity, clippy::many_single_char_names)]
+
 extern crate glutin;
 extern crate gfx2d;
-extern crate byteorder;
 extern crate time;
 extern crate nalgebra as na;
 extern crate ini;
 extern crate typenum;
 extern crate bit_array;
-
-macro_rules! iif(($cond:expr, $then:expr, $otherwise:expr) => (if $cond { $then } else { $otherwise }));
+#[macro_use]
+extern crate soldank;
 
 use na::Vector2;
+use std::collections::HashMap;
 use glutin::*;
 use gfx2d::*;
 
-use shared::anims::Animation;
-use shared::parts::ParticleSystem;
+use soldank::shared;
 use shared::mapfile::MapFile;
-use shared::state::*;
-use shared::soldier::*;
-use shared::render::*;
-
-mod shared;
-
-const GRAV: f32 = 0.06;
+use shared::state::MainState;
+use shared::settings::GameSettings;
+use shared::world::World;
+use render::*;
+use shared::input::InputFrame;
+use shared::bot::BotProfile;
+use shared::appearance::PlayerAppearance;
+use keybindings::{Binding, KeyBindings};
+use client::ClientState;
+
+mod client;
+mod render;
+mod keybindings;
 
 fn main() {
-    let anims = AnimsList {
-        stand: Animation::load_from_file(&String::from("stoi.poa"), 0, 3, true),
-        run: Animation::load_from_file(&String::from("biega.poa"), 1, 1, true),
-        run_back: Animation::load_from_file(&String::from("biegatyl.poa"), 2, 1, true),
-        jump: Animation::load_from_file(&String::from("skok.poa"), 3, 1, false),
-        jump_side: Animation::load_from_file(&String::from("skokwbok.poa"), 4, 1, false),
-        fall: Animation::load_from_file(&String::from("spada.poa"), 5, 1, false),
-        crouch: Animation::load_from_file(&String::from("kuca.poa"), 6, 1, false),
-        crouch_run: Animation::load_from_file(&String::from("kucaidzie.poa"), 7, 2, true),
-        reload: Animation::load_from_file(&String::from("laduje.poa"), 8, 2, false),
-        throw: Animation::load_from_file(&String::from("rzuca.poa"), 9, 1, false),
-        recoil: Animation::load_from_file(&String::from("odrzut.poa"), 10, 1, false),
-        small_recoil: Animation::load_from_file(&String::from("odrzut2.poa"), 11, 1, false),
-        shotgun: Animation::load_from_file(&String::from("shotgun.poa"), 12, 1, false),
-        clip_out: Animation::load_from_file(&String::from("clipout.poa"), 13, 3, false),
-        clip_in: Animation::load_from_file(&String::from("clipin.poa"), 14, 3, false),
-        slide_back: Animation::load_from_file(&String::from("slideback.poa"), 15, 2, false),
-        change: Animation::load_from_file(&String::from("change.poa"), 16, 0, false),
-        throw_weapon: Animation::load_from_file(&String::from("wyrzuca.poa"), 17, 1, false),
-        weapon_none: Animation::load_from_file(&String::from("bezbroni.poa"), 18, 3, false),
-        punch: Animation::load_from_file(&String::from("bije.poa"), 19, 0, false),
-        reload_bow: Animation::load_from_file(&String::from("strzala.poa"), 20, 1, false),
-        barret: Animation::load_from_file(&String::from("barret.poa"), 21, 9, false),
-        roll: Animation::load_from_file(&String::from("skokdolobrot.poa"), 22, 1, false),
-        roll_back: Animation::load_from_file(&String::from("skokdolobrottyl.poa"), 23, 1, false),
-        crouch_run_back: Animation::load_from_file(&String::from("kucaidzietyl.poa"), 24, 2, true),
-        cigar: Animation::load_from_file(&String::from("cigar.poa"), 25, 3, false),
-        match_: Animation::load_from_file(&String::from("match.poa"), 26, 3, false),
-        smoke: Animation::load_from_file(&String::from("smoke.poa"), 27, 4, false),
-        wipe: Animation::load_from_file(&String::from("wipe.poa"), 28, 4, false),
-        groin: Animation::load_from_file(&String::from("krocze.poa"), 29, 2, false),
-        piss: Animation::load_from_file(&String::from("szcza.poa"), 30, 8, false),
-        mercy: Animation::load_from_file(&String::from("samo.poa"), 31, 3, false),
-        mercy2: Animation::load_from_file(&String::from("samo2.poa"), 32, 3, false),
-        take_off: Animation::load_from_file(&String::from("takeoff.poa"), 33, 2, false),
-        prone: Animation::load_from_file(&String::from("lezy.poa"), 34, 1, false),
-        victory: Animation::load_from_file(&String::from("cieszy.poa"), 35, 3, false),
-        aim: Animation::load_from_file(&String::from("celuje.poa"), 36, 2, false),
-        hands_up_aim: Animation::load_from_file(&String::from("gora.poa"), 37, 2, false),
-        prone_move: Animation::load_from_file(&String::from("lezyidzie.poa"), 38, 2, true),
-        get_up: Animation::load_from_file(&String::from("wstaje.poa"), 39, 1, false),
-        aim_recoil: Animation::load_from_file(&String::from("celujeodrzut.poa"), 40, 1, false),
-        hands_up_recoil: Animation::load_from_file(&String::from("goraodrzut.poa"), 41, 1, false),
-        melee: Animation::load_from_file(&String::from("kolba.poa"), 42, 1, false),
-        own: Animation::load_from_file(&String::from("rucha.poa"), 43, 3, false),
-    };
-    let mut gostek = ParticleSystem::new();
-    gostek.load_from_file(&String::from("gostek.po"), 4.50);
-    gostek.timestep = 1.00;
-    gostek.gravity = 1.06 * GRAV;
-    gostek.v_damping = 0.9945;
-
-    let mut soldier_parts = ParticleSystem::new();
-
-    soldier_parts.timestep = 1.0;
-    soldier_parts.gravity = GRAV;
-    soldier_parts.e_damping = 0.99;
-
     let map = MapFile::load_map_file(&String::from("ctf_Ash.pms"));
+    let settings = GameSettings::load_from_file("assets/game.ini");
+    let mut bot_rng = settings.rng();
 
     const W: u32 = 1280;
     const H: u32 = 720;
 
-    let mut state = MainState {
-        map: map,
-        anims: anims,
-        soldier_parts: soldier_parts,
-        gostek_skeleton: gostek,
+    let mut world = World::new(MainState::new(map, settings));
+    let team = world.join_team();
+    let player = world.add_soldier(team).unwrap();
+
+    let mut client = ClientState {
+        player,
         game_width: W as f32 * (480.0 / H as f32),
         game_height: 480.0,
         camera: Vector2::new(0.0f32, 0.0f32),
         camera_prev: Vector2::new(0.0f32, 0.0f32),
         mouse: Vector2::new(0.0f32, 0.0f32),
         mouse_prev: Vector2::new(0.0f32, 0.0f32),
-        gravity: GRAV,
         zoom: 0.0,
     };
 
-    let mut soldier = Soldier::new(&mut state);
-    state.camera = state.soldier_parts.pos[1];
+    if let Some(soldier) = world.soldier_mut(player) {
+        soldier.set_appearance(PlayerAppearance::load_from_file("assets/player.ini"));
+    }
+
+    let profiles = BotProfile::load_all("assets/bots");
+    let mut bots = world.add_bots(&profiles);
+
+    for bot in &bots {
+        println!("{} joined the game", bot.profile.name);
+    }
+
+    let bindings = KeyBindings::load_from_file("assets/controls.ini");
+    let mut input = InputFrame::default();
+    let mut inputs = HashMap::new();
+    client.camera = world.state.soldier_parts.pos[player];
 
     // setup window, renderer & main loop
 
@@ -116,7 +81,7 @@ fn main() {
 
     let mut graphics = GameGraphics::new(&mut context);
     graphics.load_sprites(&mut context);
-    graphics.load_map(&mut context, &state.map);
+    graphics.load_map(&mut context, &world.state.map);
 
     let time_start = time::precise_time_s();
     let current_time = || {time::precise_time_s() - time_start};
@@ -130,33 +95,39 @@ fn main() {
     let mut zoomout_pressed = false;
 
     while running {
-        context.evt.poll_events(|event| match event {
-            Event::WindowEvent{event, ..} => match event {
-                WindowEvent::Closed => running = false,
-                WindowEvent::KeyboardInput{input, ..} => {
-                    match input.virtual_keycode {
-                        Some(VirtualKeyCode::Escape) => running = false,
-                        Some(VirtualKeyCode::Add) => zoomin_pressed = match input.state {
-                            ElementState::Pressed => true,
-                            ElementState::Released => false,
-                        },
-                        Some(VirtualKeyCode::Subtract) => zoomout_pressed = match input.state {
-                            ElementState::Pressed => true,
-                            ElementState::Released => false,
-                        },
-                        _ => soldier.update_keys(&input),
-                    }
-                },
-                WindowEvent::MouseInput{state, button, ..} => {
-                    soldier.update_mouse_button(&(state, button));
-                },
-                WindowEvent::CursorMoved{position: (x, y), ..} => {
-                    state.mouse.x = x as f32 * state.game_width / W as f32;
-                    state.mouse.y = y as f32 * state.game_height / H as f32;
-                },
-                _ => (),
-            },
-            _ => (),
+        context.evt.poll_events(|event| {
+            if let Event::WindowEvent{event, ..} = event {
+                match event {
+                    WindowEvent::Closed => running = false,
+                    WindowEvent::KeyboardInput{input: key, ..} => {
+                        match key.virtual_keycode {
+                            Some(VirtualKeyCode::Escape) => running = false,
+                            Some(VirtualKeyCode::Add) => zoomin_pressed = match key.state {
+                                ElementState::Pressed => true,
+                                ElementState::Released => false,
+                            },
+                            Some(VirtualKeyCode::Subtract) => zoomout_pressed = match key.state {
+                                ElementState::Pressed => true,
+                                ElementState::Released => false,
+                            },
+                            Some(code) => {
+                                let pressed = key.state == ElementState::Pressed;
+                                bindings.apply(&mut input, Binding::Key(code), pressed);
+                            },
+                            None => (),
+                        }
+                    },
+                    WindowEvent::MouseInput{state, button, ..} => {
+                        let pressed = state == ElementState::Pressed;
+                        bindings.apply(&mut input, Binding::Mouse(button), pressed);
+                    },
+                    WindowEvent::CursorMoved{position: (x, y), ..} => {
+                        client.mouse.x = x as f32 * client.game_width / W as f32;
+                        client.mouse.y = y as f32 * client.game_height / H as f32;
+                    },
+                    _ => (),
+                }
+            }
         });
 
         let dt = 1.0/60.0;
@@ -168,31 +139,42 @@ fn main() {
         while timeacc >= dt {
             timeacc -= dt;
 
-            state.soldier_parts.do_eurler_timestep_for(1);
-            soldier.update(&mut state);
+            input.mouse_aim_x = (client.mouse.x - client.game_width / 2.0 + client.camera.x).round() as i32;
+            input.mouse_aim_y = (client.mouse.y - client.game_height / 2.0 + client.camera.y).round() as i32;
 
-            state.soldier_parts.old_pos[2] = state.soldier_parts.pos[2];
-            state.soldier_parts.pos[2].x += 50.0 * dt as f32;
+            inputs.insert(player, input);
+
+            for bot in &mut bots {
+                inputs.insert(bot.num, bot.think(&world, &mut bot_rng));
+            }
+
+            world.step(&inputs);
+
+            for event in world.state.events.drain(..) {
+                if let Some(line) = event.log_line() {
+                    println!("{}", line);
+                }
+            }
 
-            state.camera_prev = state.camera;
-            state.mouse_prev = state.mouse;
+            client.camera_prev = client.camera;
+            client.mouse_prev = client.mouse;
 
             if zoomin_pressed ^ zoomout_pressed {
-                state.zoom += iif!(zoomin_pressed, -1.0, 1.0) * dt as f32;
+                client.zoom += iif!(zoomin_pressed, -1.0, 1.0) * dt as f32;
             }
 
-            state.camera = {
-                let z = f32::exp(state.zoom);
+            client.camera = {
+                let z = f32::exp(client.zoom);
                 let mut m = Vec2::zeros();
 
-                m.x = z * (state.mouse.x - state.game_width / 2.0) / 7.0
-                    * ((2.0 * 640.0 / state.game_width - 1.0)
-                    + (state.game_width - 640.0) / state.game_width * 0.0 / 6.8);
-                m.y = z * (state.mouse.y - state.game_height / 2.0) / 7.0;
+                m.x = z * (client.mouse.x - client.game_width / 2.0) / 7.0
+                    * ((2.0 * 640.0 / client.game_width - 1.0)
+                    + (client.game_width - 640.0) / client.game_width * 0.0 / 6.8);
+                m.y = z * (client.mouse.y - client.game_height / 2.0) / 7.0;
 
-                let mut cam_v = state.camera;
+                let mut cam_v = client.camera;
 
-                let p = vec2(state.soldier_parts.pos[1].x, state.soldier_parts.pos[1].y);
+                let p = vec2(world.state.soldier_parts.pos[player].x, world.state.soldier_parts.pos[player].y);
                 let norm = p - cam_v;
                 let s = norm * 0.14;
                 cam_v += s;
@@ -206,7 +188,7 @@ fn main() {
         }
 
         let p = f64::min(1.0, f64::max(0.0, timeacc/dt));
-        graphics.render_frame(&mut context, &state, &soldier, timecur - dt*(1.0 - p), p as f32);
+        graphics.render_frame(&mut context, &client, &world, timecur - dt*(1.0 - p), p as f32);
         context.present();
 
         // only sleep if no vsync (or if vsync doesn't wait), also needs timeBeginPeriod(1)
@@ -0,0 +1,388 @@
+use super::*;
+use client::ClientState;
+use shared::world::World;
+use shared::soldier::VISIBLE_TIME;
+use shared::spawn::TEAM_NONE;
+use shared::mapfile::MapFile;
+use shared::spark::SparkKind;
+use shared::weapons::{BulletStyle, WeaponKind};
+use shared::thing::ThingKind;
+use shared::kit::KitKind;
+use shared::appearance::team_color;
+use std::str::FromStr;
+use std::collections::HashMap;
+use ini::Ini;
+
+/// Number of `Spark::FlamesExplode` frames a fire spark goes through.
+const FLAME_FRAMES: usize = 16;
+
+/// Applies one scale section of a mod.ini: a `DefaultScale` and per-file overrides, keyed by
+/// the file name relative to `assets/`.
+fn load_scale(section: &str, data: &HashMap<String, String>, sprites: &mut [&mut SpriteInfo]) {
+    let fname = |sprite_info: &SpriteInfo| {
+        sprite_info.filename.strip_prefix("assets/").unwrap().to_str().unwrap().to_owned()
+    };
+
+    let parse = |key: &str| data.get(key).and_then(|scale| f32::from_str(scale.trim()).ok());
+    let default_scale = parse("DefaultScale");
+
+    for sprite_info in sprites.iter_mut() {
+        if let Some(scale) = parse(&fname(sprite_info)).or(default_scale) {
+            sprite_info.pixel_ratio = vec2(scale, scale);
+        }
+    }
+
+    for key in data.keys() {
+        if key != "DefaultScale" && !sprites.iter().any(|sprite_info| fname(sprite_info) == *key) {
+            println!("Unknown mod.ini key [{}] {}", section, key);
+        }
+    }
+}
+
+/// Sprite a bullet or grenade is drawn with. Punches and knife stabs aren't drawn at all.
+fn projectile_sprite(style: BulletStyle, weapon: WeaponKind) -> Option<Weapon> {
+    match style {
+        BulletStyle::Plain => Some(match weapon {
+            WeaponKind::Eagle    => Weapon::DeaglesBullet,
+            WeaponKind::Mp5      => Weapon::Mp5Bullet,
+            WeaponKind::Ak74     => Weapon::Ak74Bullet,
+            WeaponKind::SteyrAug => Weapon::SteyrBullet,
+            WeaponKind::Ruger77  => Weapon::RugerBullet,
+            WeaponKind::Barrett  => Weapon::BarrettBullet,
+            WeaponKind::Minimi   => Weapon::MinimiBullet,
+            WeaponKind::Minigun  => Weapon::MinigunBullet,
+            WeaponKind::Socom    => Weapon::ColtBullet,
+            _ => Weapon::Bullet,
+        }),
+        BulletStyle::Shotgun        => Some(Weapon::SpasBullet),
+        BulletStyle::M79            => Some(Weapon::M79Bullet),
+        BulletStyle::Law            => Some(Weapon::Missile),
+        BulletStyle::Arrow          => Some(Weapon::Arrow),
+        BulletStyle::FlameArrow     => Some(Weapon::Arrow),
+        BulletStyle::FragGrenade    => Some(Weapon::FragGrenade),
+        BulletStyle::ClusterGrenade => Some(Weapon::ClusterGrenade),
+        BulletStyle::Cluster        => Some(Weapon::Cluster),
+        BulletStyle::ThrownKnife    => Some(Weapon::Knife),
+        BulletStyle::Flame | BulletStyle::Punch | BulletStyle::Blade => None,
+    }
+}
+
+/// Sprite a weapon lying around is drawn with.
+fn weapon_sprite(kind: WeaponKind) -> Option<Weapon> {
+    match kind {
+        WeaponKind::Eagle    => Some(Weapon::Deagles),
+        WeaponKind::Mp5      => Some(Weapon::Mp5),
+        WeaponKind::Ak74     => Some(Weapon::Ak74),
+        WeaponKind::SteyrAug => Some(Weapon::Steyr),
+        WeaponKind::Spas12   => Some(Weapon::Spas),
+        WeaponKind::Ruger77  => Some(Weapon::Ruger),
+        WeaponKind::M79      => Some(Weapon::M79),
+        WeaponKind::Barrett  => Some(Weapon::Barrett),
+        WeaponKind::Minimi   => Some(Weapon::Minimi),
+        WeaponKind::Minigun  => Some(Weapon::Minigun),
+        WeaponKind::Socom    => Some(Weapon::Socom),
+        WeaponKind::Knife    => Some(Weapon::Knife),
+        WeaponKind::Chainsaw => Some(Weapon::Chainsaw),
+        WeaponKind::Law      => Some(Weapon::Law),
+        WeaponKind::FlameBow => Some(Weapon::Bow),
+        WeaponKind::Bow      => Some(Weapon::Bow),
+        WeaponKind::Flamer   => Some(Weapon::Flamer),
+        _ => None,
+    }
+}
+
+fn kit_sprite(kind: KitKind) -> Object {
+    match kind {
+        KitKind::Medikit      => Object::Medikit,
+        KitKind::Grenadekit   => Object::Grenadekit,
+        KitKind::Clusterkit   => Object::Clusterkit,
+        KitKind::Vestkit      => Object::Vestkit,
+        KitKind::Flamerkit    => Object::Flamerkit,
+        KitKind::Berserkerkit => Object::Berserkerkit,
+        KitKind::Predatorkit  => Object::Predatorkit,
+    }
+}
+
+/// Adds a sprite turned to point along `dir`, with `pivot` (as a fraction of the sprite's size)
+/// placed at `pos`.
+fn add_pointing<T: SpriteData>(batch: &mut DrawBatch, sprites: &[Vec<Sprite>], sprite: &T,
+    color: Color, pos: Vec2, dir: Vec2, pivot: (f32, f32))
+{
+    let sprite = &sprites[sprite.group().id()][sprite.id()];
+
+    batch.add_tinted_sprite(sprite, color, Transform::WithPivot {
+        pivot: vec2(pivot.0 * sprite.width, pivot.1 * sprite.height),
+        pos,
+        scale: vec2(1.0, 1.0),
+        rot: f32::atan2(dir.y, dir.x),
+    });
+}
+
+pub struct GameGraphics {
+    map: MapGraphics,
+    gostek: GostekGraphics,
+    sprites: Vec<Vec<Sprite>>,
+    batch: DrawBatch,
+}
+
+impl GameGraphics {
+    pub fn new(_context: &mut Gfx2dContext) -> GameGraphics {
+        GameGraphics {
+            map: MapGraphics::empty(),
+            gostek: GostekGraphics::new(),
+            sprites: Vec::new(),
+            batch: DrawBatch::new(),
+        }
+    }
+
+    pub fn render_frame(&mut self, context: &mut Gfx2dContext, client: &ClientState, world: &World,
+        _elapsed: f64, _frame_percent: f32)
+    {
+        let z = f32::exp(client.zoom);
+        let (w, h) = (z*client.game_width, z*client.game_height);
+        let dx = client.camera.x - w/2.0;
+        let dy = client.camera.y - h/2.0;
+        let transform = Transform::ortho(dx, dx + w, dy, dy + h).matrix();
+
+        context.clear(rgb(0, 0, 0));
+        context.draw(self.map.background(), &Transform::ortho(0.0, 1.0, dy, dy + h).matrix());
+        context.draw(self.map.polys_back(), &transform);
+        context.draw(self.map.scenery_back(), &transform);
+
+        self.batch.clear();
+
+        // Things are drawn along the stick they're simulated as
+        for thing in world.things.iter().filter(|thing| thing.active) {
+            let dir = thing.skeleton.pos[2] - thing.skeleton.pos[1];
+
+            match thing.kind {
+                ThingKind::Weapon(weapon) => if let Some(sprite) = weapon_sprite(weapon.kind) {
+                    add_pointing(&mut self.batch, &self.sprites, &sprite, rgb(255, 255, 255),
+                        thing.position(), dir, (0.5, 0.5));
+                },
+                ThingKind::Kit(kind) => {
+                    add_pointing(&mut self.batch, &self.sprites, &kit_sprite(kind), rgb(255, 255, 255),
+                        thing.position(), dir, (0.5, 0.5));
+                },
+                ThingKind::Flag(_) => (),
+            }
+        }
+
+        // A pole held at its bottom, with the cloth hanging off its top in the team's color
+        for flag in &world.flags {
+            let (bottom, top) = (flag.thing.skeleton.pos[1], flag.thing.skeleton.pos[2]);
+            let pole = top - bottom;
+            let color = team_color(flag.team).map_or(rgb(255, 255, 255), |c| rgb(c.r, c.g, c.b));
+
+            add_pointing(&mut self.batch, &self.sprites, &Object::FlagHandle, rgb(255, 255, 255),
+                bottom, pole, (0.0, 0.5));
+            add_pointing(&mut self.batch, &self.sprites, &Object::Flag, color,
+                top, vec2(-pole.y, pole.x), (0.0, 0.0));
+        }
+
+        // Nobody fades out of their own sight, nor of their teammates'
+        let viewer = world.soldier(client.player);
+
+        for soldier in &world.soldiers {
+            let friendly = viewer.map_or(false, |viewer| {
+                viewer.num == soldier.num || (viewer.team != TEAM_NONE && viewer.team == soldier.team)
+            });
+
+            let seen = if friendly { VISIBLE_TIME } else { soldier.visible };
+            self.gostek.render(soldier, &mut self.batch, &self.sprites,
+                world.state.settings.realistic_mode, seen);
+        }
+
+        // Bullets trail behind their position, grenades spin around theirs
+        for bullet in world.bullets.iter().filter(|bullet| bullet.active) {
+            if let Some(sprite) = projectile_sprite(bullet.style, bullet.weapon) {
+                add_pointing(&mut self.batch, &self.sprites, &sprite, rgb(255, 255, 255),
+                    bullet.pos, bullet.velocity, (1.0, 0.5));
+            }
+        }
+
+        for grenade in world.grenades.iter().filter(|grenade| grenade.active) {
+            if let Some(sprite) = projectile_sprite(grenade.style, grenade.weapon) {
+                add_pointing(&mut self.batch, &self.sprites, &sprite, rgb(255, 255, 255),
+                    grenade.pos, grenade.pos - grenade.old_pos, (0.5, 0.5));
+            }
+        }
+
+        for spark in &world.sparks {
+            let progress = spark.progress();
+
+            let (sprite, alpha) = match spark.kind {
+                SparkKind::Fire => {
+                    let frame = usize::min((progress * FLAME_FRAMES as f32) as usize, FLAME_FRAMES - 1);
+                    (Spark::FlamesExplode1 + frame, 255)
+                }
+                SparkKind::Smoke => (Spark::Blacksmoke, (128.0 * (1.0 - progress)) as u8),
+            };
+
+            let sprite = &self.sprites[sprite.group().id()][sprite.id()];
+
+            self.batch.add_tinted_sprite(sprite, rgba(255, 255, 255, alpha), Transform::WithPivot {
+                pivot: vec2(sprite.width / 2.0, sprite.height / 2.0),
+                pos: spark.pos,
+                scale: vec2(1.0, 1.0),
+                rot: 0.0,
+            });
+        }
+
+        context.draw(self.batch.all(), &transform);
+
+        context.draw(self.map.scenery_mid(), &transform);
+        context.draw(self.map.polys_front(), &transform);
+        context.draw(self.map.scenery_front(), &transform);
+
+        // skeleton points
+        if false {
+            self.batch.clear();
+
+            let px = h / context.wnd.get_inner_size().unwrap().1 as f32;
+            for soldier in &world.soldiers {
+                let n = soldier.skeleton.constraint_count as usize;
+
+                for constraint in &soldier.skeleton.constraints[1..=n] {
+                    let a = soldier.skeleton.pos[constraint.part_a as usize];
+                    let b = soldier.skeleton.pos[constraint.part_b as usize];
+
+                    let m = Transform::WithPivot {
+                        pos: a,
+                        pivot: vec2(0.0, 0.0),
+                        scale: vec2(::shared::calc::distance(a, b), 1.0),
+                        rot: ::na::angle(&(b - a), &vec2(1.0, 0.0)),
+                    }.matrix();
+
+                    self.batch.add_quads(None, &[[
+                        vertex(m * vec2(0.0, -0.5 * px), Vec2::zeros(), rgb(255, 255, 0)),
+                        vertex(m * vec2(1.0, -0.5 * px), Vec2::zeros(), rgb(255, 255, 0)),
+                        vertex(m * vec2(1.0,  0.5 * px), Vec2::zeros(), rgb(255, 255, 0)),
+                        vertex(m * vec2(0.0,  0.5 * px), Vec2::zeros(), rgb(255, 255, 0)),
+                    ]]);
+                }
+
+                for p in &soldier.skeleton.pos[1..25] {
+                    let m = Mat2d::translate(p.x, p.y);
+
+                    self.batch.add_quads(None, &[[
+                        vertex(m * vec2(-0.75 * px, -0.75 * px), Vec2::zeros(), rgb(0, 0, 255)),
+                        vertex(m * vec2( 0.75 * px, -0.75 * px), Vec2::zeros(), rgb(0, 0, 255)),
+                        vertex(m * vec2( 0.75 * px,  0.75 * px), Vec2::zeros(), rgb(0, 0, 255)),
+                        vertex(m * vec2(-0.75 * px,  0.75 * px), Vec2::zeros(), rgb(0, 0, 255)),
+                    ]]);
+                }
+            }
+
+            context.draw(self.batch.all(), &transform);
+        }
+
+        // cursor
+        {
+            let size = context.wnd.get_inner_size().unwrap();
+            let size = vec2(size.0 as f32, size.1 as f32);
+            let x = z * f32::floor(client.mouse.x * size.x / w);
+            let y = z * f32::floor(client.mouse.y * size.y / h);
+            let screen = Transform::ortho(0.0, size.x, 0.0, size.y).matrix();
+
+            self.batch.clear();
+            self.batch.add_quads(None, &[
+                [
+                    vertex(vec2(x, y) + vec2(0.0, -8.0), Vec2::zeros(), rgb(0, 0, 0)),
+                    vertex(vec2(x, y) + vec2(1.0, -8.0), Vec2::zeros(), rgb(0, 0, 0)),
+                    vertex(vec2(x, y) + vec2(1.0,  9.0), Vec2::zeros(), rgb(0, 0, 0)),
+                    vertex(vec2(x, y) + vec2(0.0,  9.0), Vec2::zeros(), rgb(0, 0, 0)),
+                ],
+                [
+                    vertex(vec2(x, y) + vec2(-8.0, 0.0), Vec2::zeros(), rgb(0, 0, 0)),
+                    vertex(vec2(x, y) + vec2( 9.0, 0.0), Vec2::zeros(), rgb(0, 0, 0)),
+                    vertex(vec2(x, y) + vec2( 9.0, 1.0), Vec2::zeros(), rgb(0, 0, 0)),
+                    vertex(vec2(x, y) + vec2(-8.0, 1.0), Vec2::zeros(), rgb(0, 0, 0)),
+                ],
+            ]);
+
+            context.draw(self.batch.all(), &screen);
+        }
+    }
+
+    pub fn load_map(&mut self, context: &mut Gfx2dContext, map: &MapFile) {
+        self.map = MapGraphics::new(context, map);
+    }
+
+    pub fn load_sprites(&mut self, context: &mut Gfx2dContext) {
+        let mut main: Vec<SpriteInfo> = Vec::new();
+        let mut intf: Vec<SpriteInfo> = Vec::new();
+        let mut main_groups: Vec<usize> = Vec::new();
+
+        let add_to = |v: &mut Vec<SpriteInfo>, fname: &str| {
+            let fname = filename_override("assets/", fname);
+            v.push(SpriteInfo::new(fname, vec2(1.0, 1.0), None));
+        };
+
+        for group in SpriteGroup::values() {
+            match *group {
+                SpriteGroup::Gostek    => Gostek   ::values().iter().map(|v| sprite_filename(*v)).for_each(|f| add_to(&mut main, f)),
+                SpriteGroup::Weapon    => Weapon   ::values().iter().map(|v| v.filename()).for_each(|f| add_to(&mut main, f)),
+                SpriteGroup::Spark     => Spark    ::values().iter().map(|v| v.filename()).for_each(|f| add_to(&mut main, f)),
+                SpriteGroup::Object    => Object   ::values().iter().map(|v| v.filename()).for_each(|f| add_to(&mut main, f)),
+                SpriteGroup::Interface => Interface::values().iter().map(|v| v.filename()).for_each(|f| add_to(&mut intf, f)),
+            }
+
+            main_groups.resize(main.len(), group.id());
+        }
+
+        if let Ok(cfg) = Ini::load_from_file("assets/mod.ini") {
+            self.gostek.load_data(&cfg);
+
+            // [SCALE] applies to everything, [WEAPONS] and [INTERFACE] take precedence over it
+            // for their own sprites
+            if let Some(data) = cfg.section(Some("SCALE".to_owned())) {
+                load_scale("SCALE", data, &mut main.iter_mut().chain(intf.iter_mut()).collect::<Vec<_>>());
+            }
+
+            if let Some(data) = cfg.section(Some("WEAPONS".to_owned())) {
+                let weapon = SpriteGroup::Weapon.id();
+
+                load_scale("WEAPONS", data, &mut main.iter_mut().zip(&main_groups)
+                    .filter(|&(_, &group)| group == weapon)
+                    .map(|(sprite_info, _)| sprite_info)
+                    .collect::<Vec<_>>());
+            }
+
+            if let Some(data) = cfg.section(Some("INTERFACE".to_owned())) {
+                load_scale("INTERFACE", data, &mut intf.iter_mut().collect::<Vec<_>>());
+            }
+        }
+
+        let main = Spritesheet::new(context, 8, FilterMethod::Trilinear, &main);
+        let intf = Spritesheet::new(context, 8, FilterMethod::Trilinear, &intf);
+
+        self.sprites.clear();
+        self.sprites.resize(SpriteGroup::values().len(), Vec::new());
+
+        let mut imain = 0;
+        let mut iintf = 0;
+
+        for group in SpriteGroup::values() {
+            let index = group.id();
+
+            match *group {
+                SpriteGroup::Gostek => for _ in Gostek::values() {
+                    self.sprites[index].push(main.sprites[imain].clone()); imain += 1;
+                }
+                SpriteGroup::Weapon => for _ in Weapon::values() {
+                    self.sprites[index].push(main.sprites[imain].clone()); imain += 1;
+                }
+                SpriteGroup::Spark => for _ in Spark::values() {
+                    self.sprites[index].push(main.sprites[imain].clone()); imain += 1;
+                }
+                SpriteGroup::Object => for _ in Object::values() {
+                    self.sprites[index].push(main.sprites[imain].clone()); imain += 1;
+                }
+                SpriteGroup::Interface => for _ in Interface::values() {
+                    self.sprites[index].push(intf.sprites[iintf].clone()); iintf += 1;
+                }
+            }
+        }
+    }
+}
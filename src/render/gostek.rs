@@ -0,0 +1,479 @@
+use super::*;
+use shared::soldier::{Soldier, VISIBLE_TIME};
+use shared::appearance::{Chain, HairStyle, Headgear};
+use shared::mapfile::MapColor;
+use shared::spawn::{TEAM_BRAVO, TEAM_DELTA};
+use shared::weapons::WeaponKind;
+use shared::control::{CIGAR_LIT, CIGAR_MOUTH};
+use ini::Ini;
+use bit_array::BitArray;
+use typenum::U256;
+use std::str::FromStr;
+
+type BitSet = BitArray<u64, U256>;
+
+/// How far flexible parts like limbs may be stretched, so ragdolls don't turn into spaghetti.
+const MAX_STRETCH: f32 = 1.5;
+
+/// The Team2 version of a regular gostek sprite, worn by the bravo and delta teams on the parts
+/// flagged `team`. Both lists are kept in the same order in `sprite_data.rs`.
+pub fn team2_sprite(sprite: Gostek) -> Gostek {
+    if sprite.id() <= Gostek::Lecistopa2.id() {
+        sprite + (Gostek::Team2Stopa.id() - Gostek::Stopa.id())
+    } else {
+        sprite
+    }
+}
+
+/// The image file a gostek sprite is loaded from. Mods don't have to ship Team2 graphics, any
+/// that are missing are replaced by the regular ones.
+pub fn sprite_filename(sprite: Gostek) -> &'static str {
+    let team2 = sprite.id() >= Gostek::Team2Stopa.id() && sprite.id() <= Gostek::Team2Lecistopa2.id();
+
+    if team2 && !filename_override("assets/", sprite.filename()).exists() {
+        (sprite - (Gostek::Team2Stopa.id() - Gostek::Stopa.id())).filename()
+    } else {
+        sprite.filename()
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum GostekSprite {
+    None,
+    Gostek(Gostek),
+    Weapon(Weapon),
+}
+
+impl GostekSprite {
+    pub fn is_none(self) -> bool {
+        match self {
+            GostekSprite::None => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum GostekColor {
+    None,
+    Main,
+    Pants,
+    Skin,
+    Hair,
+    Cygar,
+    Headblood,
+}
+
+impl GostekColor {
+    pub fn from_name(name: &str) -> Option<GostekColor> {
+        match name.to_lowercase().as_str() {
+            "none"      => Some(GostekColor::None),
+            "main"      => Some(GostekColor::Main),
+            "pants"     => Some(GostekColor::Pants),
+            "skin"      => Some(GostekColor::Skin),
+            "hair"      => Some(GostekColor::Hair),
+            "cygar"     => Some(GostekColor::Cygar),
+            "headblood" => Some(GostekColor::Headblood),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum GostekAlpha {
+    Base,
+    Blood,
+    Nades,
+}
+
+impl GostekAlpha {
+    pub fn from_name(name: &str) -> Option<GostekAlpha> {
+        match name.to_lowercase().as_str() {
+            "base"  => Some(GostekAlpha::Base),
+            "blood" => Some(GostekAlpha::Blood),
+            "nades" => Some(GostekAlpha::Nades),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct GostekPartInfo {
+    pub name: &'static str,
+    pub sprite: GostekSprite,
+    pub point: (usize, usize),
+    pub center: (f32, f32),
+    /// Bone length at which a flexible part is drawn at its natural size. Flexible parts are
+    /// stretched along the bone to the actual joint distance, up to `MAX_STRETCH`. Rigid parts
+    /// have 0.
+    pub flexibility: f32,
+    pub flip: bool,
+    pub team: bool,
+    pub color: GostekColor,
+    pub alpha: GostekAlpha,
+    pub visible: bool,
+}
+
+pub struct GostekGraphics {
+    pub data: Vec<GostekPartInfo>,
+    pub base_visibility: BitSet,
+}
+
+impl GostekGraphics {
+    pub fn new() -> GostekGraphics {
+        GostekGraphics {
+            data: GostekPart::data().to_vec(),
+            base_visibility: BitSet::from_fn(|i| GostekPart::data().get(i).map_or(false, |p| p.visible)),
+        }
+    }
+
+    /// Applies the `[GOSTEK]` section of a mod.ini. Parts are named like in Soldat, with
+    /// underscores between words (`Left_Thigh`), and each can override its `_CenterX`,
+    /// `_CenterY`, `_Visible`, `_Flexibility`, `_Color` and `_Alpha`.
+    pub fn load_data(&mut self, cfg: &Ini) {
+        self.data = GostekPart::data().to_vec();
+
+        if let Some(data) = cfg.section(Some("GOSTEK".to_owned())) {
+            let copy_and_insert_underscores = |dest: &mut String, source: &str| {
+                for (i, ch) in source.chars().enumerate() {
+                    if i > 0 && ch.is_uppercase() { dest.push('_') };
+                    dest.push(ch);
+                }
+            };
+
+            let names: Vec<String> = self.data.iter().map(|part| {
+                let mut name = String::with_capacity(64);
+                copy_and_insert_underscores(&mut name, part.name);
+                name
+            }).collect();
+
+            for (key, value) in data {
+                let part = key.rfind('_').and_then(|i| {
+                    let (name, property) = (&key[..i], &key[i + 1..]);
+                    names.iter().position(|n| n == name).map(|index| (index, property))
+                });
+
+                let (index, property) = match part {
+                    Some(part) => part,
+                    None => { println!("Unknown mod.ini key [GOSTEK] {}", key); continue; },
+                };
+
+                let part = &mut self.data[index];
+                let value = value.trim();
+
+                let known = match property {
+                    "CenterX"     => f32::from_str(value).map(|v| part.center.0 = v).is_ok(),
+                    "CenterY"     => f32::from_str(value).map(|v| part.center.1 = v).is_ok(),
+                    "Flexibility" => f32::from_str(value).map(|v| part.flexibility = v).is_ok(),
+                    "Visible"     => u8::from_str(value).map(|v| part.visible = v != 0).is_ok(),
+                    "Color"       => GostekColor::from_name(value).map(|v| part.color = v).is_some(),
+                    "Alpha"       => GostekAlpha::from_name(value).map(|v| part.alpha = v).is_some(),
+                    _ => { println!("Unknown mod.ini key [GOSTEK] {}", key); continue; },
+                };
+
+                if !known {
+                    println!("Invalid value for mod.ini key [GOSTEK] {}: {}", key, value);
+                }
+            }
+        }
+
+        let data = &self.data;
+        self.base_visibility = BitSet::from_fn(|i| data.get(i).map_or(false, |p| p.visible));
+    }
+
+    /// `seen` is `Soldier::visible` as far as the viewer is concerned, soldiers fade out as it
+    /// runs out.
+    pub fn render(&self, soldier: &Soldier, batch: &mut DrawBatch, sprites: &[Vec<Sprite>],
+        realistic_mode: bool, seen: u8)
+    {
+        let mut visible = self.base_visibility.clone();
+        let alpha_base = if seen < VISIBLE_TIME {
+            u8::min(soldier.alpha, 3 * seen)
+        } else {
+            soldier.alpha
+        };
+        let mut alpha_blood = f32::max(0.0, f32::min(255.0, 200.0 - soldier.health.round())) as u8;
+        let mut color_cygar = rgb(255, 255, 255);
+        let color_none      = rgb(255, 255, 255);
+        let appearance = &soldier.appearance;
+        let color = |c: MapColor| rgb(c.r, c.g, c.b);
+        let color_main      = color(appearance.shirt_color);
+        let color_pants     = color(appearance.pants_color);
+        let color_skin      = color(appearance.skin_color);
+        let color_hair      = color(appearance.hair_color);
+        let color_headblood = rgb(172, 169, 168);
+        let alpha_nades: u8;
+        let team2 = soldier.team == TEAM_BRAVO || soldier.team == TEAM_DELTA;
+
+        if soldier.has_cigar == CIGAR_MOUTH {
+            color_cygar = rgb(97, 97, 97);
+        }
+
+        if soldier.health > (90.0 - 40.0 * f32::from(realistic_mode as u8)) {
+            alpha_blood = 0;
+        }
+
+        // Soldiers fading out of sight don't show their wounds
+        if seen < VISIBLE_TIME {
+            alpha_blood = 0;
+        }
+
+        alpha_nades = (0.75 * f32::from(alpha_base)) as u8;
+
+        if alpha_blood > 0 {
+            visible.set(GostekPart::LeftThighDmg.id(), true);
+            visible.set(GostekPart::LeftLowerlegDmg.id(), true);
+            visible.set(GostekPart::LeftForearmDmg.id(), true);
+            visible.set(GostekPart::LeftArmDmg.id(), true);
+            visible.set(GostekPart::ChestDmg.id(), true);
+            visible.set(GostekPart::HipDmg.id(), true);
+            visible.set(GostekPart::HeadDmg.id(), true);
+            visible.set(GostekPart::RightThighDmg.id(), true);
+            visible.set(GostekPart::RightLowerlegDmg.id(), true);
+            visible.set(GostekPart::RightForearmDmg.id(), true);
+            visible.set(GostekPart::RightArmDmg.id(), true);
+        }
+
+        if soldier.control.jets && soldier.jets_count > 0 {
+            visible.set(GostekPart::LeftFoot.id(), false);
+            visible.set(GostekPart::RightFoot.id(), false);
+            visible.set(GostekPart::LeftJetfoot.id(), true);
+            visible.set(GostekPart::RightJetfoot.id(), true);
+        }
+
+        if soldier.vest > 0.0 {
+            visible.set(GostekPart::Vest.id(), true);
+        }
+
+        let index = if soldier.tertiary_weapon.kind == WeaponKind::FragGrenade {
+            GostekPart::FragGrenade1.id()
+        } else {
+            GostekPart::ClusterGrenade1.id()
+        };
+
+        // The grenade being thrown is in the hand, not on the belt
+        let tertiary_ammo_count = soldier.tertiary_weapon.ammo_count;
+        let n = i32::min(5, tertiary_ammo_count - iif!(soldier.grenade_charge > 0, 1, 0));
+
+        for i in 0..n {
+            visible.set(index + i as usize, true);
+        }
+
+        // TODO: chains seem broken, check skeleton
+        match appearance.chain {
+            Chain::Silver => {
+                visible.set(GostekPart::SilverLchain.id(), true);
+                visible.set(GostekPart::SilverRchain.id(), true);
+                visible.set(GostekPart::SilverPendant.id(), true);
+            },
+            Chain::Golden => {
+                visible.set(GostekPart::GoldenLchain.id(), true);
+                visible.set(GostekPart::GoldenRchain.id(), true);
+                visible.set(GostekPart::GoldenPendant.id(), true);
+            },
+            Chain::None => {}
+        }
+
+        if soldier.has_cigar == CIGAR_MOUTH || soldier.has_cigar == CIGAR_LIT {
+            visible.set(GostekPart::Cigar.id(), true);
+        }
+
+        if soldier.dead_meat {
+            visible.set(GostekPart::Head.id(), false);
+            visible.set(GostekPart::HeadDmg.id(), false);
+            visible.set(GostekPart::HeadDead.id(), true);
+            visible.set(GostekPart::HeadDeadDmg.id(), true);
+        }
+
+        if soldier.primary_weapon.kind.is_bow() {
+            visible.set(GostekPart::RamboBadge.id(), true);
+        } else {
+            const ANIM_WIPE: i32 = 28;
+            const ANIM_TAKEOFF: i32 = 33;
+
+            let grabbed = match soldier.body_animation.id {
+                ANIM_WIPE | ANIM_TAKEOFF => soldier.body_animation.curr_frame > 4,
+                _ => false,
+            };
+
+            if soldier.wear_helmet == 1 {
+                match appearance.headgear {
+                    Headgear::Helmet if  grabbed => visible.set(GostekPart::GrabbedHelmet.id(), true),
+                    Headgear::Hat    if  grabbed => visible.set(GostekPart::GrabbedHat.id(), true),
+                    Headgear::Helmet if !grabbed => visible.set(GostekPart::Helmet.id(), true),
+                    Headgear::Hat    if !grabbed => visible.set(GostekPart::Hat.id(), true),
+                    _ => {},
+                }
+            }
+
+            let hair_style = appearance.hair_style;
+
+            if grabbed || soldier.wear_helmet != 1 || hair_style == HairStyle::MrT {
+                match hair_style {
+                    HairStyle::Dreadlocks => for i in 0..6 { visible.set(GostekPart::HairDreadlocks.id() + i, true); },
+                    HairStyle::Punk       => visible.set(GostekPart::HairPunk.id(), true),
+                    HairStyle::MrT        => visible.set(GostekPart::MrT.id(), true),
+                    HairStyle::Normal     => visible.set(GostekPart::HairNormal.id(), true),
+                    HairStyle::Bald       => {},
+                }
+            }
+        }
+
+        // The secondary weapon is carried on the back, grenades and bare hands aren't shown
+        let secondary = soldier.secondary_weapon.kind;
+
+        if secondary.id() <= WeaponKind::Flamer.id() {
+            visible.set(GostekPart::SecondaryDeagles.id() + secondary.id(), true);
+        }
+
+        if !soldier.dead_meat {
+            const ANIM_CLIP_OUT: i32 = 13;
+            const ANIM_CLIP_IN: i32 = 14;
+            const ANIM_RELOAD_BOW: i32 = 20;
+
+            let weapon = &soldier.primary_weapon;
+            let anim = &soldier.body_animation;
+
+            // The magazine is out of the gun from halfway through pulling it out until halfway
+            // through putting the new one in
+            let clip_out = weapon.is_reloading() && match anim.id {
+                ANIM_CLIP_OUT => anim.curr_frame > anim.num_frames / 2,
+                ANIM_CLIP_IN  => anim.curr_frame <= anim.num_frames / 2,
+                _ => false,
+            };
+
+            let primary = match weapon.kind {
+                WeaponKind::Eagle    => Some(GostekPart::PrimaryDeagles),
+                WeaponKind::Mp5      => Some(GostekPart::PrimaryMp5),
+                WeaponKind::Ak74     => Some(GostekPart::PrimaryAk74),
+                WeaponKind::SteyrAug => Some(GostekPart::PrimarySteyr),
+                WeaponKind::Spas12   => Some(GostekPart::PrimarySpas),
+                WeaponKind::Ruger77  => Some(GostekPart::PrimaryRuger),
+                WeaponKind::M79      => Some(GostekPart::PrimaryM79),
+                WeaponKind::Barrett  => Some(GostekPart::PrimaryBarrett),
+                WeaponKind::Minimi   => Some(GostekPart::PrimaryMinimi),
+                WeaponKind::Socom    => Some(GostekPart::PrimarySocom),
+                WeaponKind::Knife    => Some(GostekPart::PrimaryKnife),
+                WeaponKind::Chainsaw => Some(GostekPart::PrimaryChainsaw),
+                WeaponKind::Law      => Some(GostekPart::PrimaryLaw),
+                WeaponKind::Flamer   => Some(GostekPart::PrimaryFlamer),
+                _ => None,
+            };
+
+            if let Some(part) = primary {
+                // Each of these is followed by its clip and muzzle flash parts
+                visible.set(part.id(), true);
+
+                if !clip_out {
+                    visible.set(part.id() + 1, true);
+                }
+
+                if soldier.fired {
+                    visible.set(part.id() + 2, true);
+                }
+            } else if weapon.kind == WeaponKind::Minigun {
+                visible.set(GostekPart::PrimaryMinigun.id(), true);
+
+                if !weapon.is_reloading() {
+                    visible.set(GostekPart::PrimaryMinigunClip.id(), true);
+                }
+
+                if soldier.fired {
+                    visible.set(GostekPart::PrimaryMinigunFire.id(), true);
+                }
+            } else if weapon.kind.is_bow() {
+                if weapon.ammo_count == 0 {
+                    visible.set(GostekPart::PrimaryBowArrowReload.id(), true);
+                } else {
+                    visible.set(GostekPart::PrimaryBowArrow.id(), true);
+                }
+
+                if anim.id == ANIM_RELOAD_BOW {
+                    visible.set(GostekPart::PrimaryBowReload.id(), true);
+                    visible.set(GostekPart::PrimaryBowStringReload.id(), true);
+                } else {
+                    visible.set(GostekPart::PrimaryBow.id(), true);
+                    visible.set(GostekPart::PrimaryBowString.id(), true);
+                }
+
+                if soldier.fired {
+                    visible.set(GostekPart::PrimaryBowFire.id(), true);
+                }
+            }
+        }
+
+        for (i, part) in self.data.iter().enumerate() {
+            if visible[i] && !part.sprite.is_none() {
+                let mut sprite_index: usize = 0;
+                let mut cx = part.center.0;
+                let mut cy = part.center.1;
+                let mut scale = vec2(1.0, 1.0);
+                let p0 = soldier.skeleton.pos[part.point.0];
+                let p1 = soldier.skeleton.pos[part.point.1];
+                let rot = f32::atan2(p1.y - p0.y, p1.x - p0.x);
+
+                if soldier.direction != 1 {
+                    if part.flip {
+                        cy = 1.0 - cy;
+                        sprite_index += 1;
+                    } else {
+                        scale.y = -1.0;
+                    }
+                }
+
+                if part.flexibility > 0.0 {
+                    scale.x = f32::min(MAX_STRETCH, f32::sqrt((p1.x - p0.x).powi(2) + (p1.y - p0.y).powi(2)) / part.flexibility);
+                }
+
+                let mut color = match part.color {
+                    GostekColor::None      => color_none,
+                    GostekColor::Main      => color_main,
+                    GostekColor::Pants     => color_pants,
+                    GostekColor::Skin      => color_skin,
+                    GostekColor::Hair      => color_hair,
+                    GostekColor::Cygar     => color_cygar,
+                    GostekColor::Headblood => color_headblood,
+                };
+
+                match part.alpha {
+                    GostekAlpha::Base  => color.set_a(alpha_base),
+                    GostekAlpha::Blood => color.set_a(alpha_blood),
+                    GostekAlpha::Nades => color.set_a(alpha_nades),
+                };
+
+                match part.sprite {
+                    GostekSprite::Gostek(gostek_sprite) => {
+                        let mut gostek_sprite = gostek_sprite + sprite_index;
+
+                        if team2 && part.team {
+                            gostek_sprite = team2_sprite(gostek_sprite);
+                        }
+
+                        let sprite = &sprites[gostek_sprite.group().id()][gostek_sprite.id()];
+                        let (w, h) = (sprite.width, sprite.height);
+
+                        batch.add_tinted_sprite(sprite, color, Transform::WithPivot {
+                            pivot: vec2(cx * w, cy * h),
+                            pos: vec2(p0.x, p0.y + 1.0),
+                            scale,
+                            rot,
+                        });
+                    },
+                    GostekSprite::Weapon(weapon_sprite) => {
+                        let weapon_sprite = weapon_sprite + sprite_index;
+                        let sprite = &sprites[weapon_sprite.group().id()][weapon_sprite.id()];
+                        let (w, h) = (sprite.width, sprite.height);
+
+                        batch.add_tinted_sprite(sprite, color, Transform::WithPivot {
+                            pivot: vec2(cx * w, cy * h),
+                            pos: vec2(p0.x, p0.y + 1.0),
+                            scale,
+                            rot,
+                        });
+                    },
+                    GostekSprite::None => {}
+                }
+            }
+        }
+    }
+}
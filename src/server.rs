@@ -0,0 +1,134 @@
+extern crate ini;
+extern crate soldank;
+
+use ini::Ini;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use soldank::shared;
+use shared::mapfile::MapFile;
+use shared::state::MainState;
+use shared::settings::GameSettings;
+use shared::world::World;
+use shared::events::GameEvent;
+use shared::bot::BotProfile;
+use shared::net::{Message, NetServer, RejectReason, ServerEvent};
+
+const TICKS_PER_SECOND: u32 = 60;
+
+/// Settings from the `[SERVER]` section of `server.ini`. The game rules come from `game.ini`,
+/// just like for the client.
+struct ServerSettings {
+    port: u16,
+    map: String,
+}
+
+impl ServerSettings {
+    fn load_from_file(path: &str) -> ServerSettings {
+        let mut settings = ServerSettings {
+            port: 23073,
+            map: String::from("ctf_Ash"),
+        };
+
+        if let Ok(cfg) = Ini::load_from_file(path) {
+            if let Some(data) = cfg.section(Some("SERVER".to_owned())) {
+                if let Some(value) = data.get("Port") {
+                    settings.port = u16::from_str(value.trim()).unwrap_or(settings.port);
+                }
+
+                if let Some(value) = data.get("Map") {
+                    settings.map = value.trim().to_owned();
+                }
+            }
+        }
+
+        settings
+    }
+}
+
+fn main() {
+    let config = ServerSettings::load_from_file("assets/server.ini");
+    let map = MapFile::load_map_file(&format!("{}.pms", config.map));
+    let settings = GameSettings::load_from_file("assets/game.ini");
+    let mut bot_rng = settings.rng();
+    let mut world = World::new(MainState::new(map, settings));
+
+    let profiles = BotProfile::load_all("assets/bots");
+    let mut bots = world.add_bots(&profiles);
+
+    for bot in &bots {
+        println!("{} joined the game", bot.profile.name);
+    }
+
+    let mut net = NetServer::bind(("0.0.0.0", config.port)).expect("Failed to bind server socket");
+    println!("Serving {} on port {}", config.map, config.port);
+
+    let mut inputs = HashMap::new();
+    let dt = Duration::new(0, 1_000_000_000 / TICKS_PER_SECOND);
+    let mut next_tick = Instant::now();
+
+    loop {
+        for event in net.receive(world.tick) {
+            match event {
+                ServerEvent::ConnectRequest(addr) => {
+                    let team = world.join_team();
+
+                    match world.add_soldier(team) {
+                        Some(num) => {
+                            println!("Player {} joined the game from {}", num, addr);
+                            net.accept(addr, num, world.tick);
+                            net.broadcast(&Message::Joined { soldier: num as u8 });
+                        },
+                        None => {
+                            println!("Server full, rejecting {}", addr);
+                            net.reject(addr, RejectReason::Full);
+                        },
+                    }
+                },
+                ServerEvent::Disconnected(num) => {
+                    println!("Player {} left the game", num);
+                    world.remove_soldier(num);
+                    net.broadcast(&Message::Left { soldier: num as u8 });
+                },
+                ServerEvent::Message(num, Message::Chat { text, .. }) => {
+                    println!("Player {}: {}", num, text);
+                    net.broadcast(&Message::Chat { soldier: num as u8, text });
+                },
+                ServerEvent::Message(..) => (),
+            }
+        }
+
+        inputs.clear();
+        net.inputs(&mut inputs);
+
+        for bot in &mut bots {
+            inputs.insert(bot.num, bot.think(&world, &mut bot_rng));
+        }
+
+        world.step(&inputs);
+
+        for event in world.state.events.drain(..) {
+            if let Some(line) = event.log_line() {
+                println!("{}", line);
+            }
+
+            if let GameEvent::Kill { killer, victim, .. } = event {
+                net.broadcast(&Message::Kill { killer: killer.map(|num| num as u8), victim: victim as u8 });
+            }
+        }
+
+        net.send_snapshots(&world);
+
+        // Don't try to catch up on ticks missed while the machine was busy
+        next_tick += dt;
+        let now = Instant::now();
+
+        if next_tick > now {
+            thread::sleep(next_tick - now);
+        } else {
+            next_tick = now;
+        }
+    }
+}
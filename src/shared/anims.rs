@@ -96,12 +96,12 @@ impl Animation {
     }
 
     Box::new(Animation {
-      id: id,
-      num_frames: num_frames,
-      speed: speed,
+      id,
+      num_frames,
+      speed,
       count: 0,
       curr_frame: 1,
-      looped: looped,
+      looped,
       frame: new_frame,
     })
   }
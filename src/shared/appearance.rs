@@ -0,0 +1,149 @@
+use ini::Ini;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use shared::mapfile::MapColor;
+use shared::spawn::{TEAM_ALPHA, TEAM_BRAVO, TEAM_CHARLIE, TEAM_DELTA};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum HairStyle {
+  Bald,
+  Dreadlocks,
+  Punk,
+  MrT,
+  Normal,
+}
+
+/// What a soldier wears on its head when it has its helmet on.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Headgear {
+  None,
+  Helmet,
+  Hat,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Chain {
+  None,
+  Silver,
+  Golden,
+}
+
+/// How a soldier looks, as set up in a player profile or a `.bot` file.
+#[derive(Debug, Copy, Clone)]
+pub struct PlayerAppearance {
+  pub shirt_color: MapColor,
+  pub pants_color: MapColor,
+  pub skin_color: MapColor,
+  pub hair_color: MapColor,
+  pub hair_style: HairStyle,
+  pub headgear: Headgear,
+  pub chain: Chain,
+}
+
+fn color(r: u8, g: u8, b: u8) -> MapColor {
+  MapColor { r, g, b, a: 255 }
+}
+
+/// Parses a Delphi color like `$00BBGGRR`.
+fn parse_color(value: &str) -> Option<MapColor> {
+  let value = u32::from_str_radix(value.trim_start_matches('$'), 16).ok()?;
+  Some(color(value as u8, (value >> 8) as u8, (value >> 16) as u8))
+}
+
+/// Shirt color every member of a team wears in team modes.
+pub fn team_color(team: u8) -> Option<MapColor> {
+  match team {
+    TEAM_ALPHA => Some(color(205, 0, 0)),
+    TEAM_BRAVO => Some(color(0, 0, 205)),
+    TEAM_CHARLIE => Some(color(205, 205, 0)),
+    TEAM_DELTA => Some(color(0, 205, 0)),
+    _ => None,
+  }
+}
+
+impl Default for PlayerAppearance {
+  fn default() -> PlayerAppearance {
+    PlayerAppearance {
+      shirt_color: color(0, 0, 0),
+      pants_color: color(0, 0, 0),
+      skin_color: color(230, 180, 120),
+      hair_color: color(0, 0, 0),
+      hair_style: HairStyle::MrT,
+      headgear: Headgear::Helmet,
+      chain: Chain::None,
+    }
+  }
+}
+
+impl PlayerAppearance {
+  /// Loads the `[PLAYER]` section of a player profile, falling back to the defaults for
+  /// anything missing.
+  pub fn load_from_file(path: &str) -> PlayerAppearance {
+    let mut appearance = PlayerAppearance::default();
+
+    if let Ok(cfg) = Ini::load_from_file(path) {
+      if let Some(data) = cfg.section(Some("PLAYER".to_owned())) {
+        appearance.load(data);
+      }
+    }
+
+    appearance
+  }
+
+  /// Reads the keys player profiles share with `.bot` files: `Color1` (shirt), `Color2`
+  /// (pants), `Skin_Color`, `Hair_Color`, `Hair`, `Headgear` and `Chain`.
+  pub fn load(&mut self, data: &HashMap<String, String>) {
+    {
+      let mut colors = [
+        ("Color1", &mut self.shirt_color),
+        ("Color2", &mut self.pants_color),
+        ("Skin_Color", &mut self.skin_color),
+        ("Hair_Color", &mut self.hair_color),
+      ];
+
+      for &mut (key, ref mut color) in colors.iter_mut() {
+        if let Some(value) = data.get(key).and_then(|value| parse_color(value)) {
+          **color = value;
+        }
+      }
+    }
+
+    let index = |key: &str| data.get(key).and_then(|value| u8::from_str(value.trim()).ok());
+
+    if let Some(hair) = index("Hair") {
+      self.hair_style = match hair {
+        1 => HairStyle::Dreadlocks,
+        2 => HairStyle::Punk,
+        3 => HairStyle::MrT,
+        4 => HairStyle::Normal,
+        _ => HairStyle::Bald,
+      };
+    }
+
+    if let Some(headgear) = index("Headgear") {
+      self.headgear = match headgear {
+        1 => Headgear::Helmet,
+        2 => Headgear::Hat,
+        _ => Headgear::None,
+      };
+    }
+
+    if let Some(chain) = index("Chain") {
+      self.chain = match chain {
+        1 => Chain::Silver,
+        2 => Chain::Golden,
+        _ => Chain::None,
+      };
+    }
+  }
+
+  /// The appearance with the shirt in the team's color, if the team has one.
+  pub fn with_team(mut self, team: u8) -> PlayerAppearance {
+    if let Some(color) = team_color(team) {
+      self.shirt_color = color;
+    }
+
+    self
+  }
+}
@@ -0,0 +1,371 @@
+use ini::Ini;
+use na::Vector2;
+use rand::{Rng, XorShiftRng};
+use std::fs;
+use std::str::FromStr;
+
+use shared::appearance::PlayerAppearance;
+use shared::bullet;
+use shared::calc;
+use shared::control::Action;
+use shared::input::InputFrame;
+use shared::kit::KitKind;
+use shared::mapfile::MapFile;
+use shared::soldier::{self, Soldier};
+use shared::spawn::TEAM_NONE;
+use shared::state::MainState;
+use shared::thing::ThingKind;
+use shared::weapons::WeaponKind;
+use shared::world::World;
+
+const SECOND: i32 = 60;
+
+/// Enemies further away than this are ignored.
+const SIGHT_DISTANCE: f32 = 600.0;
+/// Kits further away than this aren't worth the detour.
+const KIT_DISTANCE: f32 = 300.0;
+/// How close a bot has to get to a waypoint for it to count as reached.
+const WAYPOINT_REACH_X: f32 = 12.0;
+const WAYPOINT_REACH_Y: f32 = 30.0;
+/// Goals higher up than this get jumped at.
+const JUMP_HEIGHT: f32 = 40.0;
+/// Farthest a completely inaccurate bot aims off its target.
+const MAX_AIM_ERROR: f32 = 60.0;
+const AIM_ERROR_TIME: i32 = SECOND / 2;
+/// Ticks without moving after which a bot considers itself stuck.
+const STUCK_TIME: i32 = 2 * SECOND;
+/// Skeleton points bots look from and aim at.
+const HEAD: usize = 12;
+const NECK: usize = 9;
+const HIP: usize = 5;
+
+/// Soldat's secondary weapons, in the order `.bot` files number them.
+static SECONDARY_WEAPONS: &[WeaponKind] = &[
+  WeaponKind::Socom,
+  WeaponKind::Knife,
+  WeaponKind::Chainsaw,
+  WeaponKind::Law,
+];
+
+/// Looks of a bot and the weapons it likes, as given by a Soldat `.bot` file.
+#[derive(Debug, Clone)]
+pub struct BotProfile {
+  pub name: String,
+  pub favourite_weapon: WeaponKind,
+  pub secondary_weapon: WeaponKind,
+  pub appearance: PlayerAppearance,
+}
+
+impl BotProfile {
+  pub fn new(name: &str) -> BotProfile {
+    BotProfile {
+      name: name.to_owned(),
+      favourite_weapon: WeaponKind::Ak74,
+      secondary_weapon: WeaponKind::Socom,
+      appearance: PlayerAppearance::default(),
+    }
+  }
+
+  /// Loads the `[BOT]` section of a `.bot` file. Anything missing or unknown keeps the
+  /// defaults of `BotProfile::new`.
+  pub fn load_from_file(path: &str) -> Option<BotProfile> {
+    let cfg = Ini::load_from_file(path).ok()?;
+    let data = cfg.section(Some("BOT".to_owned()))?;
+    let mut profile = BotProfile::new(data.get("Name").map_or("Bot", |name| name.as_str()));
+
+    if let Some(value) = data.get("Favourite_Weapon") {
+      profile.favourite_weapon = WeaponKind::from_name(value).unwrap_or(profile.favourite_weapon);
+    }
+
+    if let Some(value) = data.get("Secondary_Weapon") {
+      let secondary = match usize::from_str(value) {
+        Ok(index) => SECONDARY_WEAPONS.get(index).cloned(),
+        Err(_) => WeaponKind::from_name(value),
+      };
+
+      profile.secondary_weapon = secondary.unwrap_or(profile.secondary_weapon);
+    }
+
+    profile.appearance.load(data);
+
+    Some(profile)
+  }
+
+  /// Loads every `.bot` file in a directory, sorted by file name.
+  pub fn load_all(dir: &str) -> Vec<BotProfile> {
+    let mut paths: Vec<String> = match fs::read_dir(dir) {
+      Ok(entries) => entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().map_or(false, |ext| ext == "bot"))
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect(),
+      Err(_) => Vec::new(),
+    };
+
+    paths.sort();
+    paths.iter().filter_map(|path| BotProfile::load_from_file(path)).collect()
+  }
+}
+
+/// A soldier controlled by the computer. Follows the map's waypoints, shoots enemies it can see
+/// and goes for kits it needs.
+pub struct Bot {
+  pub num: usize,
+  pub profile: BotProfile,
+  /// Index of the waypoint the bot is heading to, and of the one it came from.
+  waypoint: Option<usize>,
+  last_waypoint: Option<usize>,
+  target: Option<usize>,
+  reaction_counter: i32,
+  aim_error: Vector2<f32>,
+  aim_error_counter: i32,
+  stuck_counter: i32,
+  last_position: Vector2<f32>,
+}
+
+impl Bot {
+  pub fn new(num: usize, profile: BotProfile) -> Bot {
+    Bot {
+      num,
+      profile,
+      waypoint: None,
+      last_waypoint: None,
+      target: None,
+      reaction_counter: 0,
+      aim_error: Vector2::zeros(),
+      aim_error_counter: 0,
+      stuck_counter: 0,
+      last_position: Vector2::zeros(),
+    }
+  }
+
+  /// Decides the bot's input for the next tick. Bots only look at the world, they have their own
+  /// `rng` so they don't disturb the simulation.
+  pub fn think(&mut self, world: &World, rng: &mut XorShiftRng) -> InputFrame {
+    let state = &world.state;
+
+    let soldier = match world.soldier(self.num) {
+      Some(soldier) => soldier,
+      None => return InputFrame::default(),
+    };
+
+    let mut input = InputFrame::new(soldier.control.mouse_aim_x, soldier.control.mouse_aim_y);
+
+    if soldier.dead_meat {
+      self.waypoint = None;
+      self.last_waypoint = None;
+      self.target = None;
+      return input;
+    }
+
+    let position = state.soldier_parts.pos[self.num];
+    let eye = soldier.skeleton.pos[HEAD];
+
+    let target = world
+      .soldiers
+      .iter()
+      .filter(|enemy| is_enemy(soldier, enemy) && !enemy.dead_meat)
+      .map(|enemy| (enemy.num, chest(enemy)))
+      .filter(|&(_, chest)| calc::distance(eye, chest) < SIGHT_DISTANCE)
+      .filter(|&(_, chest)| bullet::line_of_sight(&state.map, soldier.team, eye, chest))
+      .min_by(|a, b| {
+        let (da, db) = (calc::distance(eye, a.1), calc::distance(eye, b.1));
+        da.partial_cmp(&db).unwrap_or(::std::cmp::Ordering::Equal)
+      });
+
+    if target.map(|(num, _)| num) != self.target {
+      self.target = target.map(|(num, _)| num);
+      self.reaction_counter = state.settings.bot_reaction_time;
+    } else if self.reaction_counter > 0 {
+      self.reaction_counter -= 1;
+    }
+
+    self.aim_error_counter -= 1;
+
+    if self.aim_error_counter <= 0 {
+      let spread = (1.0 - state.settings.bot_accuracy) * MAX_AIM_ERROR;
+      let x: f32 = rng.gen_range(-1.0, 1.0);
+      let y: f32 = rng.gen_range(-1.0, 1.0);
+      self.aim_error = Vector2::new(x, y) * spread;
+      self.aim_error_counter = AIM_ERROR_TIME;
+    }
+
+    // Kits are gone straight for, waypoints the way their flags say
+    let goal = match self.kit_goal(soldier, world) {
+      Some(kit) => Some((kit, false)),
+      None => self.waypoint_goal(state, rng, position).map(|waypoint| (waypoint, true)),
+    };
+
+    let direction = match goal {
+      Some((goal, follow_flags)) => self.steer(state, &mut input, position, goal, follow_flags),
+      None => 0.0,
+    };
+
+    match target {
+      Some((_, chest)) => {
+        let aim = chest + self.aim_error;
+        input.mouse_aim_x = aim.x.round() as i32;
+        input.mouse_aim_y = aim.y.round() as i32;
+        input.set(Action::Fire, self.reaction_counter <= 0);
+      }
+      None if direction != 0.0 => {
+        input.mouse_aim_x = (eye.x + direction * 100.0).round() as i32;
+        input.mouse_aim_y = eye.y.round() as i32;
+      }
+      None => (),
+    }
+
+    let moving = input.is_pressed(Action::Left) || input.is_pressed(Action::Right);
+
+    if moving && calc::distance(position, self.last_position) < 0.5 {
+      self.stuck_counter += 1;
+    } else {
+      self.stuck_counter = 0;
+    }
+
+    // Jump out of wherever the bot got stuck and look for a new way from there
+    if self.stuck_counter > STUCK_TIME {
+      self.stuck_counter = 0;
+      self.waypoint = None;
+      input.set(Action::Jump, true);
+    }
+
+    self.last_position = position;
+    input
+  }
+
+  /// A kit close by that the bot could use, if it can see one.
+  fn kit_goal(&self, soldier: &Soldier, world: &World) -> Option<Vector2<f32>> {
+    let state = &world.state;
+    let eye = soldier.skeleton.pos[HEAD];
+
+    let wants = |kind: KitKind| match kind {
+      KitKind::Medikit => soldier.health < soldier::max_health(&state.settings) / 2.0,
+      KitKind::Grenadekit | KitKind::Clusterkit => soldier.tertiary_weapon.ammo_count == 0,
+      KitKind::Vestkit => soldier.vest <= 0.0,
+      _ => soldier.bonus.is_none(),
+    };
+
+    world
+      .things
+      .iter()
+      .filter(|thing| match thing.kind {
+        ThingKind::Kit(kind) => thing.active && wants(kind),
+        _ => false,
+      })
+      .map(|thing| thing.position())
+      .filter(|&kit| calc::distance(eye, kit) < KIT_DISTANCE)
+      .find(|&kit| bullet::line_of_sight(&state.map, soldier.team, eye, kit))
+  }
+
+  /// Position of the waypoint to head to, picking the next one once the current one is reached.
+  fn waypoint_goal(
+    &mut self,
+    state: &MainState,
+    rng: &mut XorShiftRng,
+    position: Vector2<f32>,
+  ) -> Option<Vector2<f32>> {
+    if self.waypoint.is_none() {
+      self.waypoint = nearest_waypoint(&state.map, position);
+      self.last_waypoint = None;
+    }
+
+    let current = self.waypoint?;
+    let goal = waypoint_position(&state.map, current);
+    let delta = goal - position;
+
+    if delta.x.abs() < WAYPOINT_REACH_X && delta.y.abs() < WAYPOINT_REACH_Y {
+      let next = {
+        let map = &state.map;
+        let connections: Vec<usize> = map.waypoints[current]
+          .connections
+          .iter()
+          .filter_map(|&id| map.waypoints.iter().position(|w| w.active && w.id == id))
+          .collect();
+
+        // Don't turn back unless it's a dead end
+        let forward: Vec<usize> = connections
+          .iter()
+          .cloned()
+          .filter(|&i| Some(i) != self.last_waypoint)
+          .collect();
+
+        let choices = iif!(forward.is_empty(), connections, forward);
+
+        if choices.is_empty() {
+          None
+        } else {
+          Some(choices[rng.gen_range(0, choices.len())])
+        }
+      };
+
+      self.last_waypoint = Some(current);
+      self.waypoint = next;
+    }
+
+    self.waypoint.map(|i| waypoint_position(&state.map, i))
+  }
+
+  /// Presses the buttons that take the bot towards `goal` and returns the horizontal direction
+  /// it's going in. With `follow_flags`, the flags of the last waypoint say how to move on.
+  fn steer(
+    &self,
+    state: &MainState,
+    input: &mut InputFrame,
+    position: Vector2<f32>,
+    goal: Vector2<f32>,
+    follow_flags: bool,
+  ) -> f32 {
+    let delta = goal - position;
+    let mut left = delta.x < -WAYPOINT_REACH_X / 2.0;
+    let mut right = delta.x > WAYPOINT_REACH_X / 2.0;
+    let mut jump = delta.y < -JUMP_HEIGHT;
+
+    if let Some(last) = self.last_waypoint.filter(|_| follow_flags) {
+      let waypoint = &state.map.waypoints[last];
+
+      if waypoint.left || waypoint.right {
+        left = waypoint.left;
+        right = waypoint.right;
+      }
+
+      jump = jump || waypoint.up;
+      input.set(Action::Crouch, waypoint.down);
+      input.set(Action::Jet, waypoint.jet);
+    }
+
+    input.set(Action::Left, left && !right);
+    input.set(Action::Right, right && !left);
+    input.set(Action::Jump, jump);
+
+    match (left, right) {
+      (true, false) => -1.0,
+      (false, true) => 1.0,
+      _ => 0.0,
+    }
+  }
+}
+
+fn is_enemy(soldier: &Soldier, other: &Soldier) -> bool {
+  other.num != soldier.num && (soldier.team == TEAM_NONE || other.team != soldier.team)
+}
+
+fn chest(soldier: &Soldier) -> Vector2<f32> {
+  (soldier.skeleton.pos[NECK] + soldier.skeleton.pos[HIP]) * 0.5
+}
+
+fn waypoint_position(map: &MapFile, index: usize) -> Vector2<f32> {
+  Vector2::new(map.waypoints[index].x as f32, map.waypoints[index].y as f32)
+}
+
+fn nearest_waypoint(map: &MapFile, position: Vector2<f32>) -> Option<usize> {
+  map
+    .waypoints
+    .iter()
+    .enumerate()
+    .filter(|&(_, waypoint)| waypoint.active)
+    .map(|(i, _)| (i, calc::distance(position, waypoint_position(map, i))))
+    .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(::std::cmp::Ordering::Equal))
+    .map(|(i, _)| i)
+}
@@ -0,0 +1,276 @@
+use na::Vector2;
+
+use shared::calc;
+use shared::state::{MainState, GRAV};
+use shared::soldier::Soldier;
+use shared::mapfile::{MapFile, PolyType};
+use shared::damage::{Damage, DamageSource, BodyPart};
+use shared::events::GameEvent;
+use shared::explosion::{self, Explosion};
+use shared::weapons::{BulletStyle, WeaponKind};
+use shared::spawn::{TEAM_NONE, TEAM_ALPHA, TEAM_BRAVO, TEAM_CHARLIE, TEAM_DELTA};
+
+const BULLET_GRAVITY: f32 = GRAV * 2.25;
+const BULLET_DAMPING: f32 = 0.99;
+
+const BULLET_TIMEOUT: i32 = 7 * 60;
+const FLAME_TIMEOUT: i32 = 32;
+const MELEE_TIMEOUT: i32 = 3;
+
+/// weapons.ini gives the damage of knife stabs and punches per blow, ten times what they deal.
+const BLOW_DAMAGE_SCALE: f32 = 0.1;
+
+/// Collisions are checked every this many pixels along a bullet's path, so fast bullets can't
+/// tunnel through thin polygons or limbs.
+const STEP: f32 = 2.0;
+
+/// Skeleton segments that can be hit, with the distance from them that still counts as a hit.
+const BONES: &[(usize, usize, f32, BodyPart)] = &[
+  (9, 23, 4.0, BodyPart::Head),
+  (9, 5, 4.0, BodyPart::Torso),
+  (9, 6, 4.0, BodyPart::Torso),
+  (5, 3, 3.0, BodyPart::Legs),
+  (3, 1, 3.0, BodyPart::Legs),
+  (6, 4, 3.0, BodyPart::Legs),
+  (4, 2, 3.0, BodyPart::Legs),
+];
+
+/// Whether projectiles of the given team stop at polygons of the given type.
+pub fn collides_with(polytype: PolyType, team: u8) -> bool {
+  match polytype {
+    PolyType::Normal
+    | PolyType::OnlyBulletsCollide
+    | PolyType::Ice
+    | PolyType::Deadly
+    | PolyType::BloodyDeadly
+    | PolyType::Hurts
+    | PolyType::Regenerates
+    | PolyType::Lava
+    | PolyType::Bouncy
+    | PolyType::Explosive
+    | PolyType::HurtsFlaggers => true,
+    PolyType::AlphaBullets => team == TEAM_ALPHA,
+    PolyType::BlueBullets => team == TEAM_BRAVO,
+    PolyType::CharlieBullets => team == TEAM_CHARLIE,
+    PolyType::DeltaBullets => team == TEAM_DELTA,
+    _ => false,
+  }
+}
+
+/// Whether a bullet fired by the given team from `from` would make it to `to`.
+pub fn line_of_sight(map: &MapFile, team: u8, from: Vector2<f32>, to: Vector2<f32>) -> bool {
+  !map.ray_cast(from, to, |polytype| collides_with(polytype, team))
+}
+
+/// The body part of the soldier at `pos`, if any.
+pub fn hit_test(soldier: &Soldier, pos: Vector2<f32>) -> Option<BodyPart> {
+  BONES
+    .iter()
+    .find(|&&(a, b, radius, _)| {
+      calc::point_segment_distance(soldier.skeleton.pos[a], soldier.skeleton.pos[b], pos) < radius
+    })
+    .map(|&(_, _, _, part)| part)
+}
+
+/// Distance from `pos` to the closest point of the soldier's skeleton.
+pub fn skeleton_distance(soldier: &Soldier, pos: Vector2<f32>) -> f32 {
+  BONES.iter().fold(::std::f32::MAX, |d, &(a, b, _, _)| {
+    f32::min(d, calc::point_segment_distance(soldier.skeleton.pos[a], soldier.skeleton.pos[b], pos))
+  })
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct BulletParams {
+  pub style: BulletStyle,
+  pub weapon: WeaponKind,
+  pub owner: usize,
+  pub team: u8,
+  pub position: Vector2<f32>,
+  pub velocity: Vector2<f32>,
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Hit {
+  Map,
+  Soldier(usize, BodyPart),
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Bullet {
+  pub active: bool,
+  pub style: BulletStyle,
+  pub weapon: WeaponKind,
+  pub owner: usize,
+  pub team: u8,
+  pub pos: Vector2<f32>,
+  pub old_pos: Vector2<f32>,
+  pub velocity: Vector2<f32>,
+  pub time_out: i32,
+}
+
+impl Bullet {
+  pub fn new(params: &BulletParams) -> Bullet {
+    let time_out = match params.style {
+      BulletStyle::Punch | BulletStyle::Blade => MELEE_TIMEOUT,
+      BulletStyle::Flame => FLAME_TIMEOUT,
+      _ => BULLET_TIMEOUT,
+    };
+
+    Bullet {
+      active: true,
+      style: params.style,
+      weapon: params.weapon,
+      owner: params.owner,
+      team: params.team,
+      pos: params.position,
+      old_pos: params.position,
+      velocity: params.velocity,
+      time_out,
+    }
+  }
+
+  /// Flames and flaming arrows set whoever they hit on fire.
+  pub fn is_incendiary(&self) -> bool {
+    self.style == BulletStyle::Flame || self.style == BulletStyle::FlameArrow
+  }
+
+  /// Explosive projectiles don't hurt whoever they hit directly, they blow up instead.
+  pub fn is_explosive(&self) -> bool {
+    match self.style {
+      BulletStyle::M79
+      | BulletStyle::Law
+      | BulletStyle::FragGrenade
+      | BulletStyle::ClusterGrenade
+      | BulletStyle::Cluster => true,
+      _ => false,
+    }
+  }
+
+  pub fn update(&mut self, state: &mut MainState, soldiers: &mut [Soldier]) {
+    self.time_out -= 1;
+
+    if self.time_out <= 0 {
+      self.active = false;
+      return;
+    }
+
+    self.old_pos = self.pos;
+    self.velocity.y += BULLET_GRAVITY;
+    self.velocity *= BULLET_DAMPING;
+
+    let steps = f32::max(1.0, (calc::vec2length(self.velocity) / STEP).ceil()) as i32;
+
+    for i in 1..=steps {
+      let pos = self.old_pos + self.velocity * (i as f32 / steps as f32);
+
+      if state.map.sector_polys(pos).is_none() {
+        self.active = false;
+        return;
+      }
+
+      if let Some(hit) = self.check_collision(state, soldiers, pos) {
+        self.hit(state, soldiers, pos, hit);
+        return;
+      }
+    }
+
+    self.pos = self.old_pos + self.velocity;
+  }
+
+  fn check_collision(&self, state: &MainState, soldiers: &[Soldier], pos: Vector2<f32>) -> Option<Hit> {
+    if let Some(polys) = state.map.sector_polys(pos) {
+      for &poly in polys {
+        let w = poly as usize - 1;
+
+        if collides_with(state.map.polygons[w].polytype, self.team)
+          && state.map.point_in_poly_edges(pos.x, pos.y, w as i32)
+        {
+          return Some(Hit::Map);
+        }
+      }
+    }
+
+    for soldier in soldiers {
+      let enemy = self.team == TEAM_NONE || soldier.team != self.team
+        || state.settings.friendly_fire;
+
+      if soldier.num == self.owner || soldier.dead_meat || !enemy {
+        continue;
+      }
+
+      if let Some(part) = hit_test(soldier, pos) {
+        return Some(Hit::Soldier(soldier.num, part));
+      }
+    }
+
+    None
+  }
+
+  fn hit(&mut self, state: &mut MainState, soldiers: &mut [Soldier], pos: Vector2<f32>, hit: Hit) {
+    self.active = false;
+    self.pos = pos;
+
+    let soldier = match hit {
+      Hit::Map => None,
+      Hit::Soldier(num, part) => {
+        if !self.is_explosive() {
+          let stats = *state.weapons.get(self.weapon);
+
+          let modifier = match part {
+            BodyPart::Head => stats.modifier_head,
+            BodyPart::Torso => stats.modifier_chest,
+            BodyPart::Legs => stats.modifier_legs,
+          };
+
+          let multiplier = soldiers
+            .iter()
+            .find(|s| s.num == self.owner)
+            .map_or(1.0, |s| s.damage_multiplier());
+
+          // Bullet damage scales with speed, so bullets get weaker as they slow down
+          let base = if self.weapon.damage_per_blow() {
+            stats.damage * BLOW_DAMAGE_SCALE
+          } else {
+            stats.damage * calc::vec2length(self.velocity)
+          };
+
+          let amount = base * modifier * multiplier;
+
+          let damage = Damage::new(DamageSource::Bullet, amount)
+            .attacker(self.owner)
+            .part(part)
+            .impulse(self.velocity * stats.push);
+
+          if let Some(soldier) = soldiers.iter_mut().find(|s| s.num == num) {
+            soldier.apply_damage(state, &damage);
+            soldier.bink = i32::max(soldier.bink, stats.bink);
+
+            if self.is_incendiary() {
+              soldier.ignite(Some(self.owner));
+            }
+          }
+        }
+
+        Some(num)
+      }
+    };
+
+    state.events.push(GameEvent::Impact {
+      position: pos,
+      style: self.style,
+      soldier,
+    });
+
+    if self.is_explosive() {
+      let explosion = Explosion {
+        position: pos,
+        style: self.style,
+        weapon: self.weapon,
+        owner: self.owner,
+        team: self.team,
+      };
+
+      explosion::explode(state, soldiers, &explosion);
+    }
+  }
+}
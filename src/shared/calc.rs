@@ -26,3 +26,17 @@ pub fn point_line_distance(p1: Vector2<f32>, p2: Vector2<f32>, p3: Vector2<f32>)
 
   ((x - p3.x).powi(2) + (y - p3.y).powi(2)).sqrt()
 }
+
+/// Distance from `p3` to the segment between `p1` and `p2`.
+pub fn point_segment_distance(p1: Vector2<f32>, p2: Vector2<f32>, p3: Vector2<f32>) -> f32 {
+  let len = (p2.x - p1.x).powi(2) + (p2.y - p1.y).powi(2);
+
+  if len < 0.001 {
+    return distance(p1, p3);
+  }
+
+  let u = ((p3.x - p1.x) * (p2.x - p1.x) + (p3.y - p1.y) * (p2.y - p1.y)) / len;
+  let u = u.max(0.0).min(1.0);
+
+  distance(Vector2::new(p1.x + u * (p2.x - p1.x), p1.y + u * (p2.y - p1.y)), p3)
+}
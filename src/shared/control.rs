@@ -1,5 +1,9 @@
 use shared::state::MainState;
 use shared::soldier::*;
+use shared::input::InputFrame;
+use shared::weapons::WeaponKind;
+use std::mem;
+use rand::Rng;
 
 const POS_STAND: u8 = 1;
 const POS_CROUCH: u8 = 2;
@@ -17,6 +21,14 @@ const JETSPEED: f32 = 0.10;
 const SECOND: i32 = 60;
 
 const DEFAULT_IDLETIME: i32 = SECOND * 8;
+/// Number of different idle animations `idle_random` picks from.
+const IDLE_ANIMS: i8 = 5;
+
+/// `has_cigar` values: no cigar at all, one in the pocket, in the mouth, and lit.
+pub const CIGAR_NONE: u8 = 0;
+pub const CIGAR_POCKET: u8 = 1;
+pub const CIGAR_MOUTH: u8 = 5;
+pub const CIGAR_LIT: u8 = 10;
 
 #[derive(Default, Debug)]
 pub struct Control {
@@ -41,11 +53,35 @@ pub struct Control {
   pub was_changing_weapon: bool,
   pub was_throwing_grenade: bool,
   pub was_reloading_weapon: bool,
+  pub was_proning: bool,
 }
 
 impl Soldier {
-  pub fn control(&mut self, state: &mut MainState) {
+  pub fn control(&mut self, state: &mut MainState, input: &InputFrame) {
     let mut player_pressed_left_right = false;
+
+    self.control.apply_input(input);
+
+    // Recoil pushes the aim up around the soldier, whichever way it's facing
+    if self.recoil > 0.0 {
+      let center = state.soldier_parts.pos[self.num];
+      let dx = self.control.mouse_aim_x as f32 - center.x;
+      let dy = self.control.mouse_aim_y as f32 - center.y;
+      let (sin, cos) = iif!(dx >= 0.0, -self.recoil, self.recoil).sin_cos();
+      self.control.mouse_aim_x = (center.x + dx * cos - dy * sin).round() as i32;
+      self.control.mouse_aim_y = (center.y + dx * sin + dy * cos).round() as i32;
+    }
+
+    // After a hard landing the soldier crouches for a moment and can't move
+    if self.landing_time > 0 {
+      self.landing_time -= 1;
+      self.control.left = false;
+      self.control.right = false;
+      self.control.up = false;
+      self.control.jets = false;
+      self.control.down = true;
+    }
+
     if self.legs_animation.speed < 1 {
       self.legs_animation.speed = 1;
     }
@@ -62,11 +98,6 @@ impl Soldier {
     //   self.direction
     // );
 
-    self.control.mouse_aim_x =
-      (state.mouse.x - state.game_width as f32 / 2.0 + state.camera.x).round() as i32;
-    self.control.mouse_aim_y =
-      (state.mouse.y - state.game_height as f32 / 2.0 + state.camera.y).round() as i32;
-
     let (mut cleft, mut cright) = (self.control.left, self.control.right);
 
     // If both left and right directions are pressed, then decide which direction to go in
@@ -129,11 +160,11 @@ impl Soldier {
       self.control.was_reloading_weapon = self.control.reload;
     }
 
-    if self.dead_meat {
+    if self.dead_meat || self.half_dead {
       self.control.free_controls();
     }
 
-    //self.fired = 0;
+    self.fired = false;
     self.control.mouse_aim_x =
       (self.control.mouse_aim_x as f32 + state.soldier_parts.velocity[self.num].x).round() as i32;
     self.control.mouse_aim_y =
@@ -288,28 +319,47 @@ impl Soldier {
         self.idle_time = DEFAULT_IDLETIME;
       }
 
-      if self.idle_random == 0 {
-        if self.idle_time == 0 {
-          self.body_apply_animation(state.anims.smoke.clone(), 1);
-          self.idle_time = DEFAULT_IDLETIME;
-        }
+      if self.idle_random < 0 {
+        self.idle_random = state.rng.gen_range(0, IDLE_ANIMS);
+      }
 
-        if (self.body_animation.id == state.anims.smoke.id)
-          && (self.body_animation.curr_frame == 17)
-        {
-          self.body_animation.curr_frame += 1;
-        }
+      if self.idle_time == 0 {
+        // The cigar goes from the pocket to the mouth, gets lit and then smoked
+        let anim = match (self.idle_random, self.has_cigar) {
+          (0, CIGAR_POCKET) => state.anims.cigar.clone(),
+          (0, CIGAR_MOUTH) => state.anims.match_.clone(),
+          (0, CIGAR_LIT) => state.anims.smoke.clone(),
+          (2, _) => state.anims.groin.clone(),
+          (3, _) if self.wear_helmet == 1 => state.anims.take_off.clone(),
+          (4, _) => state.anims.piss.clone(),
+          _ => state.anims.wipe.clone(),
+        };
 
-        if !self.dead_meat {
-          if (self.idle_time == 1) && (self.body_animation.id != state.anims.smoke.id)
-            && (self.legs_animation.id == state.anims.stand.id)
-          {
-            self.idle_time = DEFAULT_IDLETIME;
-            self.idle_random = -1;
-          }
+        self.body_apply_animation(anim, 1);
+        self.idle_time = DEFAULT_IDLETIME;
+      }
+
+      if (self.body_animation.id == state.anims.smoke.id)
+        && (self.body_animation.curr_frame == 17)
+      {
+        self.body_animation.curr_frame += 1;
+      }
+
+      if self.body_animation.curr_frame == self.body_animation.num_frames {
+        if self.body_animation.id == state.anims.cigar.id {
+          self.has_cigar = CIGAR_MOUTH;
+        } else if self.body_animation.id == state.anims.match_.id {
+          self.has_cigar = CIGAR_LIT;
         }
       }
 
+      if !self.dead_meat && (self.idle_time == 1) && !self.is_idle_animation(state)
+        && (self.legs_animation.id == state.anims.stand.id)
+      {
+        self.idle_time = DEFAULT_IDLETIME;
+        self.idle_random = -1;
+      }
+
       // *CHEAT*
       if self.legs_animation.speed > 1 {
         if (self.legs_animation.id == state.anims.jump.id)
@@ -338,19 +388,13 @@ impl Soldier {
 
       // TODO if targetmode > freecontrols
       // End any ongoing idle animations if a key is pressed
-      if (self.body_animation.id == state.anims.cigar.id)
-        || (self.body_animation.id == state.anims.match_.id)
-        || (self.body_animation.id == state.anims.smoke.id)
-        || (self.body_animation.id == state.anims.wipe.id)
-        || (self.body_animation.id == state.anims.groin.id)
-      {
-        if cleft || cright || self.control.up || self.control.down
+      if (self.is_idle_animation(state) || (self.body_animation.id == state.anims.victory.id))
+        && (cleft || cright || self.control.up || self.control.down
           || self.control.fire || self.control.jets || self.control.grenade
-          || self.control.change || self.control.change || self.control.throw
-          || self.control.reload || self.control.prone
-        {
-          self.body_animation.curr_frame = self.body_animation.num_frames;
-        }
+          || self.control.change || self.control.flag_throw || self.control.throw
+          || self.control.reload || self.control.prone)
+      {
+        self.body_animation.curr_frame = self.body_animation.num_frames;
       }
 
       // make anims out of controls
@@ -735,6 +779,10 @@ impl Soldier {
         self.body_apply_animation(state.anims.stand.clone(), 1);
       }
 
+      if !self.dead_meat && !self.half_dead {
+        self.control_weapons(state);
+      }
+
       if (!self.control.grenade && (self.body_animation.id != state.anims.recoil.id)
         && (self.body_animation.id != state.anims.small_recoil.id)
         && (self.body_animation.id != state.anims.aim_recoil.id)
@@ -760,6 +808,9 @@ impl Soldier {
         && (self.body_animation.id != state.anims.victory.id)
         && (self.body_animation.id != state.anims.own.id)
         && (self.body_animation.id != state.anims.reload.id)
+        && (self.body_animation.id != state.anims.clip_out.id)
+        && (self.body_animation.id != state.anims.clip_in.id)
+        && (self.body_animation.id != state.anims.slide_back.id)
         && (self.body_animation.id != state.anims.prone.id)
         && (self.body_animation.id != state.anims.get_up.id)
         && (self.body_animation.id != state.anims.prone_move.id)
@@ -807,10 +858,200 @@ impl Soldier {
       }
     }
   }
+
+  /// Whether the body is playing one of the animations soldiers do when left alone.
+  fn is_idle_animation(&self, state: &MainState) -> bool {
+    let id = self.body_animation.id;
+
+    id == state.anims.cigar.id || id == state.anims.match_.id || id == state.anims.smoke.id
+      || id == state.anims.wipe.id || id == state.anims.groin.id
+      || id == state.anims.take_off.id || id == state.anims.piss.id
+  }
+
+  fn control_weapons(&mut self, state: &mut MainState) {
+    // Grenades charge while the key is held, keeping the arm back, and fly on release
+    if self.grenade_charge > 0 {
+      if self.body_animation.id != state.anims.throw.id {
+        self.grenade_charge = 0;
+      } else if self.control.grenade {
+        self.grenade_charge = i32::min(self.grenade_charge + 1, GRENADE_MAX_CHARGE);
+
+        let hold = self.body_animation.num_frames / 2;
+
+        if self.body_animation.curr_frame > hold {
+          self.body_animation.curr_frame = hold;
+        }
+
+        return;
+      } else {
+        self.throw_grenade(state);
+        return;
+      }
+    }
+
+    let busy = (self.body_animation.id == state.anims.change.id)
+      || (self.body_animation.id == state.anims.throw_weapon.id)
+      || (self.body_animation.id == state.anims.throw.id)
+      || (self.body_animation.id == state.anims.roll.id)
+      || (self.body_animation.id == state.anims.roll_back.id)
+      || (self.body_animation.id == state.anims.melee.id);
+
+    // Weapon change, the weapons are swapped halfway through the animation
+    if (self.body_animation.id == state.anims.change.id) && (self.body_animation.curr_frame == 2) {
+      mem::swap(&mut self.primary_weapon, &mut self.secondary_weapon);
+      self.primary_weapon.start_up_time_count = 0;
+    } else if self.control.change && !busy && !self.has_flamer() {
+      self.body_apply_animation(state.anims.change.clone(), 1);
+      return;
+    }
+
+    // Weapon throw, the weapon leaves the hand halfway through the animation
+    if (self.body_animation.id == state.anims.throw_weapon.id)
+      && (self.body_animation.curr_frame == self.body_animation.num_frames / 2)
+    {
+      self.throw_weapon(state);
+    } else if self.control.throw && !busy && (self.primary_weapon.kind != WeaponKind::Hands) {
+      self.body_apply_animation(state.anims.throw_weapon.clone(), 1);
+      return;
+    }
+
+    if busy {
+      return;
+    }
+
+    if self.control.grenade && (self.tertiary_weapon.ammo_count > 0) {
+      self.body_apply_animation(state.anims.throw.clone(), 1);
+      self.grenade_charge = 1;
+      return;
+    }
+
+    // Put the new magazine in once the old one is out
+    if (self.body_animation.id == state.anims.clip_out.id)
+      && (self.body_animation.curr_frame == self.body_animation.num_frames)
+      && self.primary_weapon.is_reloading()
+    {
+      self.body_apply_animation(state.anims.clip_in.clone(), 1);
+    }
+
+    let (ammo, start_up_time) = {
+      let stats = state.weapons.get(self.primary_weapon.kind);
+      (stats.ammo, stats.start_up_time)
+    };
+
+    if !self.primary_weapon.is_reloading() && (self.primary_weapon.ammo_count < ammo) {
+      let empty = (self.primary_weapon.ammo_count == 0)
+        && (self.primary_weapon.fire_interval_count == 0);
+
+      if self.control.reload || empty {
+        self.reload_weapon(state);
+        return;
+      }
+    }
+
+    if self.control.fire && (self.primary_weapon.fire_interval_count == 0)
+      && !self.primary_weapon.is_reloading() && (self.primary_weapon.ammo_count > 0)
+      && (self.primary_weapon.start_up_time_count >= start_up_time)
+    {
+      self.fire_weapon(state);
+    }
+  }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Action {
+  Left,
+  Right,
+  Jump,
+  Crouch,
+  Fire,
+  Jet,
+  Grenade,
+  ChangeWeapon,
+  ThrowWeapon,
+  Reload,
+  Prone,
+  ThrowFlag,
+}
+
+impl Action {
+  pub fn values() -> &'static [Action] {
+    static VALUES: &[Action] = &[
+      Action::Left,
+      Action::Right,
+      Action::Jump,
+      Action::Crouch,
+      Action::Fire,
+      Action::Jet,
+      Action::Grenade,
+      Action::ChangeWeapon,
+      Action::ThrowWeapon,
+      Action::Reload,
+      Action::Prone,
+      Action::ThrowFlag,
+    ];
+    VALUES
+  }
+
+  pub fn name(self) -> &'static str {
+    match self {
+      Action::Left => "Left",
+      Action::Right => "Right",
+      Action::Jump => "Jump",
+      Action::Crouch => "Crouch",
+      Action::Fire => "Fire",
+      Action::Jet => "Jet",
+      Action::Grenade => "Grenade",
+      Action::ChangeWeapon => "ChangeWeapon",
+      Action::ThrowWeapon => "ThrowWeapon",
+      Action::Reload => "Reload",
+      Action::Prone => "Prone",
+      Action::ThrowFlag => "ThrowFlag",
+    }
+  }
+
+  pub fn from_name(name: &str) -> Option<Action> {
+    Action::values()
+      .iter()
+      .find(|action| action.name().eq_ignore_ascii_case(name))
+      .cloned()
+  }
 }
 
 impl Control {
   pub fn free_controls(&mut self) {
     *self = Default::default();
   }
+
+  pub fn apply_input(&mut self, input: &InputFrame) {
+    for &action in Action::values() {
+      if action != Action::Prone {
+        self.set_action(action, input.is_pressed(action));
+      }
+    }
+
+    // Prone toggles, so it only triggers once per key press
+    let prone = input.is_pressed(Action::Prone);
+    self.prone = prone && !self.was_proning;
+    self.was_proning = prone;
+
+    self.mouse_aim_x = input.mouse_aim_x;
+    self.mouse_aim_y = input.mouse_aim_y;
+  }
+
+  pub fn set_action(&mut self, action: Action, pressed: bool) {
+    match action {
+      Action::Left => self.left = pressed,
+      Action::Right => self.right = pressed,
+      Action::Jump => self.up = pressed,
+      Action::Crouch => self.down = pressed,
+      Action::Fire => self.fire = pressed,
+      Action::Jet => self.jets = pressed,
+      Action::Grenade => self.grenade = pressed,
+      Action::ChangeWeapon => self.change = pressed,
+      Action::ThrowWeapon => self.throw = pressed,
+      Action::Reload => self.reload = pressed,
+      Action::Prone => self.prone = pressed,
+      Action::ThrowFlag => self.flag_throw = pressed,
+    }
+  }
 }
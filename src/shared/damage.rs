@@ -0,0 +1,62 @@
+use na::Vector2;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DamageSource {
+  Polygon,
+  Fall,
+  Bullet,
+  Explosion,
+  Fire,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BodyPart {
+  Head,
+  Torso,
+  Legs,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DeathType {
+  Normal,
+  Headchop,
+  Brutal,
+}
+
+/// A single hit on a soldier. Every damage-dealing system builds one of these and passes it to
+/// `Soldier::apply_damage`, which takes care of armor, death and kill attribution.
+#[derive(Debug, Copy, Clone)]
+pub struct Damage {
+  pub source: DamageSource,
+  pub attacker: Option<usize>,
+  pub amount: f32,
+  pub part: BodyPart,
+  pub impulse: Vector2<f32>,
+}
+
+impl Damage {
+  pub fn new(source: DamageSource, amount: f32) -> Damage {
+    Damage {
+      source,
+      attacker: None,
+      amount,
+      part: BodyPart::Torso,
+      impulse: Vector2::zeros(),
+    }
+  }
+
+  pub fn attacker(mut self, attacker: usize) -> Damage {
+    self.attacker = Some(attacker);
+    self
+  }
+
+  pub fn part(mut self, part: BodyPart) -> Damage {
+    self.part = part;
+    self
+  }
+
+  pub fn impulse(mut self, impulse: Vector2<f32>) -> Damage {
+    self.impulse = impulse;
+    self
+  }
+}
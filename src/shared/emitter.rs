@@ -0,0 +1,13 @@
+use shared::bullet::BulletParams;
+use shared::thing::ThingParams;
+use shared::spark::SparkParams;
+
+/// Objects spawned during a tick, like fired bullets or thrown grenades. Collected in
+/// `MainState::emitter` and turned into world entities by `World::step`.
+#[derive(Debug, Copy, Clone)]
+pub enum EmitterItem {
+  Bullet(BulletParams),
+  Grenade(BulletParams),
+  Thing(ThingParams),
+  Spark(SparkParams),
+}
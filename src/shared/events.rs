@@ -0,0 +1,72 @@
+use na::Vector2;
+use shared::damage::DamageSource;
+use shared::weapons::BulletStyle;
+use shared::gamemode::Winner;
+
+/// Things that happened during a tick which other systems (scoring, logging, networking)
+/// might care about. Collected in `MainState::events`.
+#[derive(Debug, Copy, Clone)]
+pub enum GameEvent {
+  Kill {
+    killer: Option<usize>,
+    victim: usize,
+    source: DamageSource,
+  },
+  Respawn {
+    soldier: usize,
+  },
+  /// A projectile hit a polygon, or a soldier if `soldier` is set.
+  Impact {
+    position: Vector2<f32>,
+    style: BulletStyle,
+    soldier: Option<usize>,
+  },
+  Explosion {
+    position: Vector2<f32>,
+    style: BulletStyle,
+  },
+  /// A soldier hit the ground falling faster than `FallDamage::hard_landing_speed`.
+  HardLanding {
+    soldier: usize,
+    speed: f32,
+  },
+  /// `team` is always the team the flag belongs to.
+  FlagGrab {
+    team: u8,
+    soldier: usize,
+  },
+  FlagDrop {
+    team: u8,
+    soldier: usize,
+  },
+  /// A flag went back to its base, returned by a teammate or by itself if `soldier` isn't set.
+  FlagReturn {
+    team: u8,
+    soldier: Option<usize>,
+  },
+  /// The flag was brought to the carrier's own base, scoring for the carrier's team.
+  FlagCapture {
+    team: u8,
+    soldier: usize,
+  },
+  RoundEnd {
+    winner: Winner,
+  },
+}
+
+impl GameEvent {
+  /// A line for the game log, for the events worth telling the players about.
+  pub fn log_line(&self) -> Option<String> {
+    match *self {
+      GameEvent::Kill { killer: Some(killer), victim, source } if killer != victim => {
+        Some(format!("Player {} killed player {} ({:?})", killer, victim, source))
+      }
+      GameEvent::Kill { victim, source, .. } => Some(format!("Player {} died ({:?})", victim, source)),
+      GameEvent::FlagCapture { team, soldier } => {
+        Some(format!("Player {} captured the flag of team {}", soldier, team))
+      }
+      GameEvent::RoundEnd { winner } => Some(format!("Round over, winner: {:?}", winner)),
+      _ => None,
+    }
+  }
+}
@@ -0,0 +1,90 @@
+use na::Vector2;
+
+use shared::bullet;
+use shared::calc;
+use shared::state::MainState;
+use shared::soldier::Soldier;
+use shared::damage::{Damage, DamageSource};
+use shared::events::GameEvent;
+use shared::weapons::{BulletStyle, WeaponKind};
+use shared::spawn::TEAM_NONE;
+
+/// Explosion damage at the center is the weapon's damage times this.
+const DAMAGE_SCALE: f32 = 0.1;
+/// Speed given to soldiers and ragdolls at the center of an explosion.
+const KNOCKBACK: f32 = 6.0;
+/// Soldiers in this fraction of an incendiary explosion's radius catch fire.
+const IGNITE_RADIUS: f32 = 0.5;
+
+#[derive(Debug, Copy, Clone)]
+pub struct Explosion {
+  pub position: Vector2<f32>,
+  pub style: BulletStyle,
+  pub weapon: WeaponKind,
+  pub owner: usize,
+  pub team: u8,
+}
+
+impl Explosion {
+  /// Blast radius and the fraction of the weapon's damage it deals.
+  fn blast(&self) -> (f32, f32) {
+    match self.style {
+      BulletStyle::FragGrenade => (90.0, 1.0),
+      BulletStyle::ClusterGrenade => (40.0, 0.3),
+      BulletStyle::Cluster => (50.0, 0.35),
+      BulletStyle::M79 => (80.0, 1.0),
+      BulletStyle::Law => (90.0, 1.0),
+      _ => (60.0, 1.0),
+    }
+  }
+
+  /// Rocket and M79 explosions set soldiers close to them on fire.
+  fn is_incendiary(&self) -> bool {
+    self.style == BulletStyle::Law || self.style == BulletStyle::M79
+  }
+}
+
+/// Damages and pushes everything within the blast radius, linearly weaker towards its edge. The
+/// owner is always hurt by its own explosions, teammates only with friendly fire.
+pub fn explode(state: &mut MainState, soldiers: &mut [Soldier], explosion: &Explosion) {
+  let (radius, fraction) = explosion.blast();
+  let multiplier = soldiers
+    .iter()
+    .find(|s| s.num == explosion.owner)
+    .map_or(1.0, |s| s.damage_multiplier());
+  let strength = state.weapons.get(explosion.weapon).damage * DAMAGE_SCALE * fraction * multiplier;
+
+  for soldier in soldiers.iter_mut() {
+    let teammate = soldier.num != explosion.owner && explosion.team != TEAM_NONE
+      && soldier.team == explosion.team;
+
+    if teammate && !state.settings.friendly_fire {
+      continue;
+    }
+
+    let distance = bullet::skeleton_distance(soldier, explosion.position);
+
+    if distance >= radius {
+      continue;
+    }
+
+    let falloff = 1.0 - distance / radius;
+    let direction = state.soldier_parts.pos[soldier.num] - explosion.position;
+    let direction = calc::vec2normalize(direction, direction);
+
+    let damage = Damage::new(DamageSource::Explosion, strength * falloff)
+      .attacker(explosion.owner)
+      .impulse(direction * KNOCKBACK * falloff);
+
+    soldier.apply_damage(state, &damage);
+
+    if explosion.is_incendiary() && distance < radius * IGNITE_RADIUS {
+      soldier.ignite(Some(explosion.owner));
+    }
+  }
+
+  state.events.push(GameEvent::Explosion {
+    position: explosion.position,
+    style: explosion.style,
+  });
+}
@@ -0,0 +1,128 @@
+use na::Vector2;
+
+use shared::mapfile::MapFile;
+use shared::soldier::Soldier;
+use shared::state::MainState;
+use shared::thing::{self, Thing, ThingKind, ThingParams};
+use shared::spawn::TEAM_NONE;
+
+const SECOND: i32 = 60;
+
+/// Ticks a dropped flag lies around before it returns to its base by itself.
+const FLAG_RETURN_TIME: i32 = 25 * SECOND;
+pub const FLAG_THROW_SPEED: f32 = 5.0;
+/// Skeleton point the bottom of a carried flag's pole is attached to.
+const FLAG_HOLD_POINT: usize = 5;
+
+/// Team value of the map spawnpoints marking the team's flag base. `TEAM_NONE` has the yellow
+/// flag.
+fn base_spawnpoint_team(team: u8) -> i32 {
+  iif!(team == TEAM_NONE, 14, i32::from(team) + 4)
+}
+
+/// Where the given team's flag sits, if the map has a base for it.
+pub fn base_position(map: &MapFile, team: u8) -> Option<Vector2<f32>> {
+  let spawn_team = base_spawnpoint_team(team);
+
+  map.spawnpoints
+    .iter()
+    .find(|s| s.active && s.team == spawn_team)
+    .map(|s| Vector2::new(s.x as f32, s.y as f32))
+}
+
+/// A team's flag. While nobody carries it, it's simulated as a thing lying around.
+pub struct Flag {
+  pub team: u8,
+  pub base: Vector2<f32>,
+  pub thing: Thing,
+  /// Soldier carrying the flag.
+  pub holder: Option<usize>,
+  /// Whether the flag hasn't been taken since it was last put at its base.
+  pub at_base: bool,
+  /// Ticks left before a dropped flag returns to its base.
+  pub return_counter: i32,
+}
+
+impl Flag {
+  pub fn new(team: u8, base: Vector2<f32>) -> Flag {
+    Flag {
+      team,
+      base,
+      thing: Flag::thing_at(team, base),
+      holder: None,
+      at_base: true,
+      return_counter: 0,
+    }
+  }
+
+  fn thing_at(team: u8, position: Vector2<f32>) -> Thing {
+    Thing::new(&ThingParams {
+      kind: ThingKind::Flag(team),
+      owner: None,
+      position,
+      velocity: Vector2::zeros(),
+    })
+  }
+
+  pub fn position(&self) -> Vector2<f32> {
+    self.thing.position()
+  }
+
+  pub fn return_to_base(&mut self) {
+    self.thing = Flag::thing_at(self.team, self.base);
+    self.holder = None;
+    self.at_base = true;
+    self.return_counter = 0;
+  }
+
+  pub fn grab(&mut self, soldier: usize) {
+    self.holder = Some(soldier);
+    self.at_base = false;
+  }
+
+  /// Lets go of the flag, which keeps moving with the given velocity. Whoever dropped it can't
+  /// grab it again right away.
+  pub fn drop(&mut self, velocity: Vector2<f32>) {
+    let skeleton = &mut self.thing.skeleton;
+
+    for i in 1..3 {
+      skeleton.old_pos[i] = skeleton.pos[i] - velocity;
+    }
+
+    self.thing.owner = self.holder;
+    self.thing.pickup_delay = thing::PICKUP_DELAY;
+    self.holder = None;
+    self.return_counter = FLAG_RETURN_TIME;
+  }
+
+  /// Moves the flag's pole along with the soldier carrying it, straight up from its hips.
+  pub fn carry(&mut self, state: &MainState, soldier: &Soldier) {
+    let bottom = soldier.skeleton.pos[FLAG_HOLD_POINT];
+    let top = bottom - Vector2::new(0.0, thing::FLAG_LENGTH);
+    let velocity = state.soldier_parts.velocity[soldier.num];
+    let skeleton = &mut self.thing.skeleton;
+
+    for &(i, pos) in &[(1, bottom), (2, top)] {
+      skeleton.pos[i] = pos;
+      skeleton.old_pos[i] = pos - velocity;
+    }
+  }
+
+  /// Simulates a flag nobody is carrying. Flags that fall out of the map or lie around for too
+  /// long go back to their base. Returns whether that happened.
+  pub fn update(&mut self, state: &mut MainState) -> bool {
+    self.thing.update(state);
+
+    if !self.at_base {
+      self.return_counter -= 1;
+    }
+
+    if !self.thing.active || (!self.at_base && self.return_counter <= 0) {
+      let returned = !self.at_base;
+      self.return_to_base();
+      return returned;
+    }
+
+    false
+  }
+}
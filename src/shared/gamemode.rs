@@ -0,0 +1,464 @@
+use std::collections::HashMap;
+use na::Vector2;
+
+use shared::state::MainState;
+use shared::soldier::Soldier;
+use shared::thing::{Thing, ThingKind, ThingParams};
+use shared::flag::Flag;
+use shared::events::GameEvent;
+use shared::settings::GameSettings;
+use shared::emitter::EmitterItem;
+use shared::weapons::{Weapon, WeaponKind};
+use shared::spawn::{TEAM_ALPHA, TEAM_BRAVO, TEAM_CHARLIE, TEAM_DELTA, TEAM_NONE};
+
+const SECOND: i32 = 60;
+
+/// Ticks between the points scored for holding (or defending) a flag.
+const FLAG_POINT_INTERVAL: i32 = 5 * SECOND;
+/// Points alpha team gets for a capture in infiltration.
+const INFILTRATION_CAPTURE_POINTS: i32 = 30;
+/// Team value of the map spawnpoints where the rambo bow appears.
+const RAMBO_BOW_SPAWNPOINT: i32 = 15;
+
+static NO_TEAMS: &[u8] = &[TEAM_NONE];
+static TWO_TEAMS: &[u8] = &[TEAM_ALPHA, TEAM_BRAVO];
+static FOUR_TEAMS: &[u8] = &[TEAM_ALPHA, TEAM_BRAVO, TEAM_CHARLIE, TEAM_DELTA];
+static NO_FLAGS: &[u8] = &[];
+static YELLOW_FLAG: &[u8] = &[TEAM_NONE];
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum GameModeKind {
+  Deathmatch,
+  TeamDeathmatch,
+  Pointmatch,
+  Rambomatch,
+  Teammatch,
+  CaptureTheFlag,
+  HoldTheFlag,
+  Infiltration,
+}
+
+impl GameModeKind {
+  pub fn values() -> &'static [GameModeKind] {
+    static VALUES: &[GameModeKind] = &[
+      GameModeKind::Deathmatch,
+      GameModeKind::TeamDeathmatch,
+      GameModeKind::Pointmatch,
+      GameModeKind::Rambomatch,
+      GameModeKind::Teammatch,
+      GameModeKind::CaptureTheFlag,
+      GameModeKind::HoldTheFlag,
+      GameModeKind::Infiltration,
+    ];
+    VALUES
+  }
+
+  /// Short name used in the settings.
+  pub fn name(self) -> &'static str {
+    match self {
+      GameModeKind::Deathmatch => "DM",
+      GameModeKind::TeamDeathmatch => "TDM",
+      GameModeKind::Pointmatch => "PM",
+      GameModeKind::Rambomatch => "RM",
+      GameModeKind::Teammatch => "TM",
+      GameModeKind::CaptureTheFlag => "CTF",
+      GameModeKind::HoldTheFlag => "HTF",
+      GameModeKind::Infiltration => "INF",
+    }
+  }
+
+  pub fn from_name(name: &str) -> Option<GameModeKind> {
+    GameModeKind::values()
+      .iter()
+      .find(|kind| kind.name().eq_ignore_ascii_case(name))
+      .cloned()
+  }
+
+  pub fn create(self) -> Box<dyn GameMode> {
+    match self {
+      GameModeKind::Deathmatch => Box::new(Deathmatch),
+      GameModeKind::TeamDeathmatch => Box::new(TeamDeathmatch),
+      GameModeKind::Pointmatch => Box::new(Pointmatch),
+      GameModeKind::Rambomatch => Box::new(Rambomatch { rambo: None }),
+      GameModeKind::Teammatch => Box::new(Teammatch),
+      GameModeKind::CaptureTheFlag => Box::new(CaptureTheFlag),
+      GameModeKind::HoldTheFlag => Box::new(HoldTheFlag { hold_counter: 0 }),
+      GameModeKind::Infiltration => Box::new(Infiltration { defend_counter: 0 }),
+    }
+  }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Winner {
+  Soldier(usize),
+  Team(u8),
+  Draw,
+}
+
+#[derive(Debug, Default, Copy, Clone)]
+pub struct PlayerScore {
+  pub kills: i32,
+  pub deaths: i32,
+  /// What the game mode counts, which isn't always the kills.
+  pub points: i32,
+}
+
+#[derive(Debug, Default)]
+pub struct Scoreboard {
+  /// Indexed by team.
+  pub teams: [i32; 5],
+  pub players: HashMap<usize, PlayerScore>,
+}
+
+impl Scoreboard {
+  pub fn player(&mut self, num: usize) -> &mut PlayerScore {
+    self.players.entry(num).or_insert_with(PlayerScore::default)
+  }
+
+  pub fn reset(&mut self) {
+    self.teams = [0; 5];
+
+    for score in self.players.values_mut() {
+      *score = PlayerScore::default();
+    }
+  }
+}
+
+/// What game mode hooks get to look at, and the scores they're there to change.
+pub struct ModeContext<'a> {
+  pub soldiers: &'a [Soldier],
+  pub things: &'a [Thing],
+  pub flags: &'a [Flag],
+  pub scores: &'a mut Scoreboard,
+}
+
+impl<'a> ModeContext<'a> {
+  pub fn team_of(&self, num: usize) -> Option<u8> {
+    self.soldiers.iter().find(|soldier| soldier.num == num).map(|soldier| soldier.team)
+  }
+
+  pub fn carries_flag(&self, num: usize) -> bool {
+    self.flags.iter().any(|flag| flag.holder == Some(num))
+  }
+}
+
+/// Rules of a game mode. `World` calls the hooks as things happen and ends the round once
+/// `winner` names one.
+pub trait GameMode {
+  fn kind(&self) -> GameModeKind;
+
+  /// Teams soldiers get split into. Just `TEAM_NONE` means everybody plays for themselves.
+  fn teams(&self) -> &'static [u8] {
+    NO_TEAMS
+  }
+
+  /// Flags placed at the start of a round, by team. `TEAM_NONE` is the yellow flag anyone can
+  /// take.
+  fn flags(&self) -> &'static [u8] {
+    NO_FLAGS
+  }
+
+  fn is_team_mode(&self) -> bool {
+    self.teams() != NO_TEAMS
+  }
+
+  /// Whether a soldier of the given team may take the given team's flag.
+  fn can_grab_flag(&self, flag_team: u8, soldier_team: u8) -> bool {
+    flag_team == TEAM_NONE || flag_team != soldier_team
+  }
+
+  fn on_spawn(&mut self, _ctx: &mut ModeContext, _soldier: usize) {}
+
+  /// Called for kills of somebody else, after they were counted on the scoreboard. Killers get a
+  /// point by default.
+  fn on_kill(&mut self, ctx: &mut ModeContext, killer: usize, _victim: usize) {
+    ctx.scores.player(killer).points += 1;
+  }
+
+  fn on_flag(&mut self, _ctx: &mut ModeContext, _event: &GameEvent) {}
+
+  fn tick(&mut self, _state: &mut MainState, _ctx: &mut ModeContext) {}
+
+  fn on_round_end(&mut self, _ctx: &mut ModeContext, _winner: Winner) {}
+
+  /// Ends the round once a team or player reaches the score limit, or with the leader when time
+  /// is up. A limit of zero means no limit.
+  fn winner(&self, scores: &Scoreboard, settings: &GameSettings, time_up: bool) -> Option<Winner> {
+    let limit = settings.score_limit;
+
+    let leaders: Vec<(Winner, i32)> = if self.is_team_mode() {
+      self.teams().iter().map(|&team| (Winner::Team(team), scores.teams[team as usize])).collect()
+    } else {
+      let mut players: Vec<(usize, i32)> =
+        scores.players.iter().map(|(&num, score)| (num, score.points)).collect();
+      players.sort();
+      players.into_iter().map(|(num, points)| (Winner::Soldier(num), points)).collect()
+    };
+
+    let best = leaders.iter().map(|&(_, points)| points).max()?;
+    let mut best_leaders = leaders.iter().filter(|&&(_, points)| points == best);
+
+    if limit > 0 && best >= limit {
+      return best_leaders.next().map(|&(winner, _)| winner);
+    }
+
+    if time_up {
+      return match (best_leaders.next(), best_leaders.next()) {
+        (Some(&(winner, _)), None) => Some(winner),
+        _ => Some(Winner::Draw),
+      };
+    }
+
+    None
+  }
+}
+
+/// Everybody for themselves, a point per kill.
+pub struct Deathmatch;
+
+impl GameMode for Deathmatch {
+  fn kind(&self) -> GameModeKind {
+    GameModeKind::Deathmatch
+  }
+}
+
+/// Two teams, every kill of an enemy scores for the killer's team.
+pub struct TeamDeathmatch;
+
+/// Scores a kill for the killer and its team, unless it was a team kill.
+fn team_kill(ctx: &mut ModeContext, killer: usize, victim: usize) {
+  match (ctx.team_of(killer), ctx.team_of(victim)) {
+    (Some(team), Some(victim_team)) if team != victim_team => {
+      ctx.scores.player(killer).points += 1;
+      ctx.scores.teams[team as usize] += 1;
+    }
+    _ => (),
+  }
+}
+
+impl GameMode for TeamDeathmatch {
+  fn kind(&self) -> GameModeKind {
+    GameModeKind::TeamDeathmatch
+  }
+
+  fn teams(&self) -> &'static [u8] {
+    TWO_TEAMS
+  }
+
+  fn on_kill(&mut self, ctx: &mut ModeContext, killer: usize, victim: usize) {
+    team_kill(ctx, killer, victim);
+  }
+}
+
+/// Like team deathmatch, with up to four teams.
+pub struct Teammatch;
+
+impl GameMode for Teammatch {
+  fn kind(&self) -> GameModeKind {
+    GameModeKind::Teammatch
+  }
+
+  fn teams(&self) -> &'static [u8] {
+    FOUR_TEAMS
+  }
+
+  fn on_kill(&mut self, ctx: &mut ModeContext, killer: usize, victim: usize) {
+    team_kill(ctx, killer, victim);
+  }
+}
+
+/// Deathmatch with a yellow flag. Kills made while carrying it are worth double.
+pub struct Pointmatch;
+
+impl GameMode for Pointmatch {
+  fn kind(&self) -> GameModeKind {
+    GameModeKind::Pointmatch
+  }
+
+  fn flags(&self) -> &'static [u8] {
+    YELLOW_FLAG
+  }
+
+  fn on_kill(&mut self, ctx: &mut ModeContext, killer: usize, _victim: usize) {
+    let points = iif!(ctx.carries_flag(killer), 2, 1);
+    ctx.scores.player(killer).points += points;
+  }
+}
+
+/// Only kills made by or of whoever has the rambo bow count. Rambo drops the bow on dying, for
+/// whoever gets to it first.
+pub struct Rambomatch {
+  /// Who had the bow at the end of the last tick. Rambo drops it on dying, so this is the only
+  /// way to tell who it was.
+  rambo: Option<usize>,
+}
+
+impl GameMode for Rambomatch {
+  fn kind(&self) -> GameModeKind {
+    GameModeKind::Rambomatch
+  }
+
+  fn on_kill(&mut self, ctx: &mut ModeContext, killer: usize, victim: usize) {
+    if self.rambo == Some(killer) || self.rambo == Some(victim) {
+      ctx.scores.player(killer).points += 1;
+    }
+  }
+
+  /// Puts the bow back at its spawnpoint whenever nobody has it and it isn't lying around.
+  fn tick(&mut self, state: &mut MainState, ctx: &mut ModeContext) {
+    let is_bow = |kind: &ThingKind| match *kind {
+      ThingKind::Weapon(weapon) => weapon.kind == WeaponKind::Bow,
+      _ => false,
+    };
+
+    self.rambo = ctx.soldiers
+      .iter()
+      .find(|soldier| !soldier.dead_meat && soldier.primary_weapon.kind == WeaponKind::Bow)
+      .map(|soldier| soldier.num);
+
+    let held = self.rambo.is_some();
+    let lying = ctx.things.iter().any(|thing| thing.active && is_bow(&thing.kind));
+    let emitted = state.emitter.iter().any(|item| match *item {
+      EmitterItem::Thing(ref params) => is_bow(&params.kind),
+      _ => false,
+    });
+
+    if held || lying || emitted {
+      return;
+    }
+
+    let position = state.map.spawnpoints
+      .iter()
+      .find(|s| s.active && s.team == RAMBO_BOW_SPAWNPOINT)
+      .map(|s| Vector2::new(s.x as f32, s.y as f32));
+
+    if let Some(position) = position {
+      let weapon = Weapon::new(WeaponKind::Bow, &state.weapons);
+
+      state.emitter.push(EmitterItem::Thing(ThingParams {
+        kind: ThingKind::Weapon(weapon),
+        owner: None,
+        position,
+        velocity: Vector2::zeros(),
+      }));
+    }
+  }
+}
+
+/// A point for the carrier's team for every capture.
+pub struct CaptureTheFlag;
+
+impl GameMode for CaptureTheFlag {
+  fn kind(&self) -> GameModeKind {
+    GameModeKind::CaptureTheFlag
+  }
+
+  fn teams(&self) -> &'static [u8] {
+    TWO_TEAMS
+  }
+
+  fn flags(&self) -> &'static [u8] {
+    TWO_TEAMS
+  }
+
+  fn on_flag(&mut self, ctx: &mut ModeContext, event: &GameEvent) {
+    if let GameEvent::FlagCapture { soldier, .. } = *event {
+      if let Some(team) = ctx.team_of(soldier) {
+        ctx.scores.teams[team as usize] += 1;
+      }
+    }
+  }
+}
+
+/// The team carrying the yellow flag scores a point every few seconds.
+pub struct HoldTheFlag {
+  hold_counter: i32,
+}
+
+impl GameMode for HoldTheFlag {
+  fn kind(&self) -> GameModeKind {
+    GameModeKind::HoldTheFlag
+  }
+
+  fn teams(&self) -> &'static [u8] {
+    TWO_TEAMS
+  }
+
+  fn flags(&self) -> &'static [u8] {
+    YELLOW_FLAG
+  }
+
+  fn tick(&mut self, _state: &mut MainState, ctx: &mut ModeContext) {
+    let holder = ctx.flags.iter().filter_map(|flag| flag.holder).next();
+
+    let team = match holder.and_then(|num| ctx.team_of(num)) {
+      Some(team) => team,
+      None => {
+        self.hold_counter = 0;
+        return;
+      }
+    };
+
+    self.hold_counter += 1;
+
+    if self.hold_counter >= FLAG_POINT_INTERVAL {
+      self.hold_counter = 0;
+      ctx.scores.teams[team as usize] += 1;
+    }
+  }
+
+  fn on_round_end(&mut self, _ctx: &mut ModeContext, _winner: Winner) {
+    self.hold_counter = 0;
+  }
+}
+
+/// Alpha attacks and scores by capturing bravo's flag, bravo scores by keeping it at its base
+/// while alpha is around to take it.
+pub struct Infiltration {
+  defend_counter: i32,
+}
+
+impl GameMode for Infiltration {
+  fn kind(&self) -> GameModeKind {
+    GameModeKind::Infiltration
+  }
+
+  fn teams(&self) -> &'static [u8] {
+    TWO_TEAMS
+  }
+
+  fn flags(&self) -> &'static [u8] {
+    TWO_TEAMS
+  }
+
+  fn can_grab_flag(&self, flag_team: u8, soldier_team: u8) -> bool {
+    flag_team == TEAM_BRAVO && soldier_team == TEAM_ALPHA
+  }
+
+  fn on_flag(&mut self, ctx: &mut ModeContext, event: &GameEvent) {
+    if let GameEvent::FlagCapture { .. } = *event {
+      ctx.scores.teams[TEAM_ALPHA as usize] += INFILTRATION_CAPTURE_POINTS;
+    }
+  }
+
+  fn tick(&mut self, _state: &mut MainState, ctx: &mut ModeContext) {
+    let defended = ctx.flags.iter().any(|flag| flag.team == TEAM_BRAVO && flag.at_base);
+    let attacked = ctx.soldiers.iter().any(|soldier| soldier.team == TEAM_ALPHA);
+
+    if !defended || !attacked {
+      self.defend_counter = 0;
+      return;
+    }
+
+    self.defend_counter += 1;
+
+    if self.defend_counter >= FLAG_POINT_INTERVAL {
+      self.defend_counter = 0;
+      ctx.scores.teams[TEAM_BRAVO as usize] += 1;
+    }
+  }
+
+  fn on_round_end(&mut self, _ctx: &mut ModeContext, _winner: Winner) {
+    self.defend_counter = 0;
+  }
+}
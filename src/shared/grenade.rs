@@ -0,0 +1,150 @@
+use na::Vector2;
+use rand::Rng;
+
+use shared::bullet::{self, BulletParams};
+use shared::calc;
+use shared::state::{MainState, GRAV};
+use shared::soldier::Soldier;
+use shared::emitter::EmitterItem;
+use shared::explosion::{self, Explosion};
+use shared::weapons::{BulletStyle, WeaponKind};
+use shared::spawn::TEAM_NONE;
+
+const GRENADE_GRAVITY: f32 = GRAV * 1.5;
+const GRENADE_DAMPING: f32 = 0.99;
+/// Fraction of the speed into a polygon that is kept when bouncing off it.
+const GRENADE_BOUNCINESS: f32 = 0.4;
+/// Fraction of the speed along a polygon that is kept when touching it.
+const GRENADE_FRICTION: f32 = 0.9;
+const GRENADE_FUSE: i32 = 3 * 60;
+
+const CLUSTERS: i32 = 5;
+const CLUSTER_SPEED: f32 = 3.0;
+
+#[derive(Debug, Copy, Clone)]
+pub struct Grenade {
+  pub active: bool,
+  pub style: BulletStyle,
+  pub weapon: WeaponKind,
+  pub owner: usize,
+  pub team: u8,
+  pub pos: Vector2<f32>,
+  pub old_pos: Vector2<f32>,
+  pub fuse: i32,
+}
+
+impl Grenade {
+  pub fn new(params: &BulletParams) -> Grenade {
+    Grenade {
+      active: true,
+      style: params.style,
+      weapon: params.weapon,
+      owner: params.owner,
+      team: params.team,
+      pos: params.position,
+      old_pos: params.position - params.velocity,
+      fuse: GRENADE_FUSE,
+    }
+  }
+
+  pub fn velocity(&self) -> Vector2<f32> {
+    self.pos - self.old_pos
+  }
+
+  pub fn update(&mut self, state: &mut MainState, soldiers: &mut [Soldier]) {
+    let mut velocity = self.velocity() * GRENADE_DAMPING;
+    velocity.y += GRENADE_GRAVITY;
+
+    self.old_pos = self.pos;
+    self.pos += velocity;
+
+    if state.map.sector_polys(self.pos).is_none() {
+      self.active = false;
+      return;
+    }
+
+    self.check_map_collision(state);
+
+    // Grenades go off as soon as they touch an enemy
+    for soldier in soldiers.iter() {
+      let enemy = self.team == TEAM_NONE || soldier.team != self.team
+        || state.settings.friendly_fire;
+
+      if soldier.num != self.owner && !soldier.dead_meat && enemy
+        && bullet::hit_test(soldier, self.pos).is_some()
+      {
+        self.fuse = 0;
+      }
+    }
+
+    self.fuse -= 1;
+
+    if self.fuse <= 0 {
+      self.explode(state, soldiers);
+    }
+  }
+
+  /// Pushes the grenade out of any polygon it ended up in and bounces it off the polygon's
+  /// closest edge.
+  fn check_map_collision(&mut self, state: &mut MainState) {
+    let polys = match state.map.sector_polys(self.pos) {
+      Some(polys) => polys.to_vec(),
+      None => return,
+    };
+
+    for poly in polys {
+      let w = poly as usize - 1;
+
+      if !bullet::collides_with(state.map.polygons[w].polytype, self.team)
+        || !state.map.point_in_poly_edges(self.pos.x, self.pos.y, w as i32)
+      {
+        continue;
+      }
+
+      let mut d = 0.0;
+      let mut k = 0;
+      let perp = state.map.closest_perpendicular(w as i32, self.pos, &mut d, &mut k);
+      let normal = calc::vec2normalize(perp, perp);
+
+      let velocity = self.velocity();
+      let along_normal = normal * velocity.dot(&normal);
+      let along_edge = velocity - along_normal;
+      let velocity = along_edge * GRENADE_FRICTION - along_normal * GRENADE_BOUNCINESS;
+
+      self.pos -= normal * d;
+      self.old_pos = self.pos - velocity;
+    }
+  }
+
+  fn explode(&mut self, state: &mut MainState, soldiers: &mut [Soldier]) {
+    self.active = false;
+
+    let explosion = Explosion {
+      position: self.pos,
+      style: self.style,
+      weapon: self.weapon,
+      owner: self.owner,
+      team: self.team,
+    };
+
+    explosion::explode(state, soldiers, &explosion);
+
+    if self.style == BulletStyle::ClusterGrenade {
+      for _ in 0..CLUSTERS {
+        let velocity = Vector2::new(
+          state.rng.gen_range(-CLUSTER_SPEED, CLUSTER_SPEED),
+          -state.rng.gen_range(CLUSTER_SPEED / 2.0, CLUSTER_SPEED * 1.5),
+        );
+
+        state.emitter.push(EmitterItem::Bullet(BulletParams {
+          style: BulletStyle::Cluster,
+          weapon: self.weapon,
+          owner: self.owner,
+          team: self.team,
+          position: self.pos,
+          velocity,
+        }));
+      }
+    }
+  }
+}
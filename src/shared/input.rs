@@ -0,0 +1,56 @@
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::io::{Read, Result, Write};
+use shared::control::Action;
+
+/// Player input for a single simulation tick. Produced by the platform layer (keyboard and
+/// mouse, network, bots or scripted input) and consumed by `Soldier::control`. The aim
+/// coordinates are in map space.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct InputFrame {
+  pub buttons: u16,
+  pub mouse_aim_x: i32,
+  pub mouse_aim_y: i32,
+}
+
+impl InputFrame {
+  pub fn new(mouse_aim_x: i32, mouse_aim_y: i32) -> InputFrame {
+    InputFrame {
+      buttons: 0,
+      mouse_aim_x,
+      mouse_aim_y,
+    }
+  }
+
+  pub fn set(&mut self, action: Action, pressed: bool) {
+    let bit = 1u16 << action as u16;
+
+    if pressed {
+      self.buttons |= bit;
+    } else {
+      self.buttons &= !bit;
+    }
+  }
+
+  pub fn is_pressed(&self, action: Action) -> bool {
+    self.buttons & (1u16 << action as u16) != 0
+  }
+
+  pub fn write<W: Write>(&self, writer: &mut W) -> Result<()> {
+    writer.write_u16::<LittleEndian>(self.buttons)?;
+    writer.write_i32::<LittleEndian>(self.mouse_aim_x)?;
+    writer.write_i32::<LittleEndian>(self.mouse_aim_y)?;
+    Ok(())
+  }
+
+  pub fn read<R: Read>(reader: &mut R) -> Result<InputFrame> {
+    let buttons = reader.read_u16::<LittleEndian>()?;
+    let mouse_aim_x = reader.read_i32::<LittleEndian>()?;
+    let mouse_aim_y = reader.read_i32::<LittleEndian>()?;
+
+    Ok(InputFrame {
+      buttons,
+      mouse_aim_x,
+      mouse_aim_y,
+    })
+  }
+}
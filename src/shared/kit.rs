@@ -0,0 +1,85 @@
+use na::Vector2;
+use rand::Rng;
+
+use shared::mapfile::MapFile;
+use shared::weapons::Weapon;
+
+const SECOND: i32 = 60;
+
+pub const BERSERKER_TIME: i32 = 15 * SECOND;
+pub const PREDATOR_TIME: i32 = 25 * SECOND;
+pub const FLAMER_TIME: i32 = 10 * SECOND;
+
+/// Damage multiplier while berserker.
+pub const BERSERKER_DAMAGE: f32 = 4.0;
+/// Soldier alpha while predator.
+pub const PREDATOR_ALPHA: u8 = 20;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum KitKind {
+  Medikit,
+  Grenadekit,
+  Clusterkit,
+  Vestkit,
+  Flamerkit,
+  Berserkerkit,
+  Predatorkit,
+}
+
+/// A timed bonus given by a kit. Flamer keeps the weapon it replaced, to give it back later.
+#[derive(Debug, Copy, Clone)]
+pub enum Bonus {
+  Berserker,
+  Predator,
+  Flamer(Weapon),
+}
+
+impl KitKind {
+  pub fn values() -> &'static [KitKind] {
+    static VALUES: &[KitKind] = &[
+      KitKind::Medikit,
+      KitKind::Grenadekit,
+      KitKind::Clusterkit,
+      KitKind::Vestkit,
+      KitKind::Flamerkit,
+      KitKind::Berserkerkit,
+      KitKind::Predatorkit,
+    ];
+    VALUES
+  }
+
+  /// Bonus kits can be turned off in the game settings, unlike medikits and grenade kits.
+  pub fn is_bonus(self) -> bool {
+    self != KitKind::Medikit && self != KitKind::Grenadekit
+  }
+
+  /// Team value of the map spawnpoints where the kit appears.
+  pub fn spawnpoint_team(self) -> i32 {
+    match self {
+      KitKind::Grenadekit => 7,
+      KitKind::Medikit => 8,
+      KitKind::Clusterkit => 9,
+      KitKind::Vestkit => 10,
+      KitKind::Flamerkit => 11,
+      KitKind::Berserkerkit => 12,
+      KitKind::Predatorkit => 13,
+    }
+  }
+}
+
+/// Picks a random spawnpoint for the kit, if the map has any.
+pub fn spawn_position<R: Rng>(map: &MapFile, kind: KitKind, rng: &mut R) -> Option<Vector2<f32>> {
+  let team = kind.spawnpoint_team();
+
+  let points: Vec<Vector2<f32>> = map.spawnpoints
+    .iter()
+    .filter(|s| s.active && s.team == team)
+    .map(|s| Vector2::new(s.x as f32, s.y as f32))
+    .collect();
+
+  if points.is_empty() {
+    None
+  } else {
+    Some(points[rng.gen_range(0, points.len())])
+  }
+}
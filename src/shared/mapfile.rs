@@ -17,6 +17,10 @@ const MAX_SECTOR: i32 = 25;
 //const MIN_TILE: i32 = MIN_SECTOR * TILESECTOR;
 //const MAX_TILE: i32 = MAX_SECTOR * TILESECTOR;
 const MAX_PROPS: i32 = 500;
+const MAX_WAYPOINTS: i32 = 5000;
+const MAX_CONNECTIONS: usize = 20;
+/// Distance between the points checked along a ray.
+const RAY_STEP: f32 = 4.0;
 //const MAX_SPAWNPOINTS: i32 = 255;
 //const MAX_COLLIDERS: i32 = 128;
 
@@ -114,6 +118,24 @@ pub struct MapSpawnpoint {
   pub y: i32,
   pub team: i32,
 }
+/// A node of the bot navigation graph. The movement flags say what to do to get from here to the
+/// connected waypoints.
+#[derive(Debug)]
+pub struct MapWaypoint {
+  pub active: bool,
+  pub id: i32,
+  pub x: i32,
+  pub y: i32,
+  pub left: bool,
+  pub right: bool,
+  pub up: bool,
+  pub down: bool,
+  pub jet: bool,
+  pub path_num: u8,
+  pub special_action: u8,
+  /// Ids of the waypoints reachable from here.
+  pub connections: Vec<i32>,
+}
 #[allow(dead_code)]
 pub struct MapFile {
   filename: String,
@@ -123,8 +145,8 @@ pub struct MapFile {
   pub bg_color_top: MapColor,
   pub bg_color_bottom: MapColor,
   pub start_jet: i32,
-  grenade_packs: u8,
-  medikits: u8,
+  pub grenade_packs: u8,
+  pub medikits: u8,
   weather: u8,
   steps: u8,
   random_id: i32,
@@ -136,6 +158,7 @@ pub struct MapFile {
   pub scenery: Vec<MapScenery>,
   colliders: Vec<MapCollider>,
   pub spawnpoints: Vec<MapSpawnpoint>,
+  pub waypoints: Vec<MapWaypoint>,
   pub sectors_poly: Vec<Vec<MapSector>>,
   pub perps: Vec<[Vector2<f32>; 3]>,
 }
@@ -222,10 +245,10 @@ impl MapFile {
       let bounciness = (normals[2].x.powi(2) + normals[2].y.powi(2)).sqrt();
 
       polygons.push(MapPolygon {
-        vertices: vertices,
-        normals: normals,
+        vertices,
+        normals,
         polytype: poly_to_enum(polytype),
-        bounciness: bounciness
+        bounciness
       });
 
       let mut perp: [Vector2<f32>; 3] = [
@@ -253,7 +276,7 @@ impl MapFile {
     for _i in 0..n {
       let m = buf.read_u16::<LittleEndian>().unwrap();
 
-      if m as i32 > MAX_POLYS {
+      if i32::from(m) > MAX_POLYS {
         break;
       }
       let mut polys: Vec<u16> = Vec::new();
@@ -269,9 +292,9 @@ impl MapFile {
 
     let sectores = vec![sector.clone(); 51];
     let mut sectored = vec![sectores.clone(); 71];
-    for i in 0..51 {
-      for j in 0..51 {
-        sectored[i][j] = sectors[k].clone();
+    for row in sectored.iter_mut().take(51) {
+      for sector in row.iter_mut() {
+        *sector = sectors[k].clone();
         k += 1;
       }
     }
@@ -321,7 +344,7 @@ impl MapFile {
       let mut filename = read_string(&mut buf, 50).ok().unwrap();
       let date = buf.read_i32::<LittleEndian>().unwrap();
       scenery.push(MapScenery {
-        filename: filename,
+        filename,
         date,
       });
     }
@@ -354,6 +377,54 @@ impl MapFile {
       spawnpoints.push(MapSpawnpoint { active, x, y, team });
     }
 
+    // Some maps out there were saved without the waypoints
+    let n = buf.read_i32::<LittleEndian>().unwrap_or(0);
+    if (n > MAX_WAYPOINTS) || (n < 0) {
+      panic!("Wrong PMS data (number of waypoints)");
+    }
+
+    let mut waypoints: Vec<MapWaypoint> = Vec::new();
+
+    for _i in 0..n {
+      let active = buf.read_u8().unwrap() != 0;
+      let mut filler = [0u8; 3];
+      buf.read_exact(&mut filler).unwrap();
+      let id = buf.read_i32::<LittleEndian>().unwrap();
+      let x = buf.read_i32::<LittleEndian>().unwrap();
+      let y = buf.read_i32::<LittleEndian>().unwrap();
+      let left = buf.read_u8().unwrap() != 0;
+      let right = buf.read_u8().unwrap() != 0;
+      let up = buf.read_u8().unwrap() != 0;
+      let down = buf.read_u8().unwrap() != 0;
+      let jet = buf.read_u8().unwrap() != 0;
+      let path_num = buf.read_u8().unwrap();
+      let special_action = buf.read_u8().unwrap();
+      let mut filler = [0u8; 5];
+      buf.read_exact(&mut filler).unwrap();
+      let connections_num = buf.read_i32::<LittleEndian>().unwrap();
+      let mut connections: Vec<i32> = Vec::new();
+      for j in 0..MAX_CONNECTIONS {
+        let connection = buf.read_i32::<LittleEndian>().unwrap();
+        if (j as i32) < connections_num {
+          connections.push(connection);
+        }
+      }
+      waypoints.push(MapWaypoint {
+        active,
+        id,
+        x,
+        y,
+        left,
+        right,
+        up,
+        down,
+        jet,
+        path_num,
+        special_action,
+        connections,
+      });
+    }
+
     MapFile {
       filename,
       version,
@@ -375,6 +446,7 @@ impl MapFile {
       scenery,
       colliders,
       spawnpoints,
+      waypoints,
       sectors_poly,
       perps,
     }
@@ -399,7 +471,45 @@ impl MapFile {
 
     true
   }
-  pub fn point_in_poly_edges(&mut self, x: f32, y: f32, i: i32) -> bool {
+  /// Polygons (1-based indices) in the sector containing `pos`, or `None` outside the map.
+  pub fn sector_polys(&self, pos: Vector2<f32>) -> Option<&[u16]> {
+    let rx = ((pos.x / self.sectors_division as f32).round()) as i32 + 25;
+    let ry = ((pos.y / self.sectors_division as f32).round()) as i32 + 25;
+
+    if (rx > 0) && (rx < self.sectors_num + 25) && (ry > 0) && (ry < self.sectors_num + 25) {
+      Some(&self.sectors_poly[rx as usize][ry as usize].polys)
+    } else {
+      None
+    }
+  }
+  /// Whether the segment from `a` to `b` passes through a polygon `collides` accepts.
+  pub fn ray_cast<F>(&self, a: Vector2<f32>, b: Vector2<f32>, collides: F) -> bool
+  where
+    F: Fn(PolyType) -> bool,
+  {
+    let delta = b - a;
+    let steps = (calc::vec2length(delta) / RAY_STEP).ceil().max(1.0) as i32;
+
+    for i in 0..=steps {
+      let p = a + delta * (i as f32 / steps as f32);
+
+      let polys = match self.sector_polys(p) {
+        Some(polys) => polys,
+        None => continue,
+      };
+
+      for &poly in polys {
+        let w = i32::from(poly) - 1;
+
+        if collides(self.polygons[w as usize].polytype) && self.point_in_poly_edges(p.x, p.y, w) {
+          return true;
+        }
+      }
+    }
+
+    false
+  }
+  pub fn point_in_poly_edges(&self, x: f32, y: f32, i: i32) -> bool {
     let u_x = x - self.polygons[i as usize].vertices[0].x;
     let u_y = y - self.polygons[i as usize].vertices[0].y;
     let d = self.perps[i as usize][0].x * u_x + self.perps[i as usize][0].y * u_y;
@@ -417,11 +527,7 @@ impl MapFile {
     let u_x = x - self.polygons[i as usize].vertices[2].x;
     let u_y = y - self.polygons[i as usize].vertices[2].y;
     let d = self.perps[i as usize][2].x * u_x + self.perps[i as usize][2].y * u_y;
-    if d < 0.0 {
-      return false;
-    }
-
-    return true;
+    d >= 0.0
   }
   pub fn closest_perpendicular(
     &mut self,
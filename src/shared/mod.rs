@@ -1,9 +1,30 @@
+#[macro_export]
+macro_rules! iif(($cond:expr, $then:expr, $otherwise:expr) => (if $cond { $then } else { $otherwise }));
+
 pub mod anims;
 pub mod parts;
 pub mod mapfile;
 pub mod soldier;
 pub mod state;
 pub mod calc;
-pub mod render;
 pub mod update;
-pub mod control;
\ No newline at end of file
+pub mod control;
+pub mod input;
+pub mod world;
+pub mod damage;
+pub mod events;
+pub mod settings;
+pub mod spawn;
+pub mod weapons;
+pub mod bullet;
+pub mod emitter;
+pub mod grenade;
+pub mod explosion;
+pub mod thing;
+pub mod spark;
+pub mod kit;
+pub mod flag;
+pub mod gamemode;
+pub mod bot;
+pub mod appearance;
+pub mod net;
@@ -0,0 +1,1249 @@
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use na::Vector2;
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, Cursor, ErrorKind, Read, Write};
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+
+use shared::input::InputFrame;
+use shared::thing::ThingKind;
+use shared::world::World;
+
+/// Every packet starts with this, anything else arriving on the socket is ignored.
+const PROTOCOL_ID: u32 = 0x534f_4c44;
+/// Bumped whenever the packet layout changes, clients and servers only talk to matching versions.
+pub const PROTOCOL_VERSION: u16 = 1;
+
+pub const MAX_PACKET_SIZE: usize = 16 * 1024;
+/// Ticks without hearing from the other side before the connection is considered lost.
+pub const CONNECTION_TIMEOUT: u32 = 10 * 60;
+/// Snapshots kept around to delta compress against, about a second's worth.
+const SNAPSHOT_HISTORY: usize = 64;
+/// Reliable messages are resent with every packet until acknowledged, this many at a time.
+const MAX_MESSAGES_PER_PACKET: usize = 8;
+const MAX_TEXT_LENGTH: usize = 255;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum RejectReason {
+  Version,
+  Full,
+}
+
+/// Messages that have to arrive, in the order they were sent. Unlike the soldiers' state, which
+/// the next snapshot brings anyway, a lost chat line or kill would be gone for good.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+  Chat { soldier: u8, text: String },
+  Kill { killer: Option<u8>, victim: u8 },
+  Joined { soldier: u8 },
+  Left { soldier: u8 },
+}
+
+/// The reliable messages riding along with a packet. `first` is the sequence number of the first
+/// of `messages`, `ack` the number of messages received from the other side so far.
+#[derive(Debug, Clone)]
+pub struct ReliableBlock {
+  pub ack: u16,
+  pub first: u16,
+  pub messages: Vec<Message>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Packet {
+  ConnectRequest { version: u16 },
+  ConnectAccept { soldier: u8 },
+  ConnectReject { reason: RejectReason },
+  Disconnect,
+  /// Sent by clients every tick. `snapshot_ack` is the newest snapshot the client has.
+  Input {
+    tick: u32,
+    input: InputFrame,
+    snapshot_ack: Option<u32>,
+    reliable: ReliableBlock,
+  },
+  /// Sent by the server every tick. `delta` is the snapshot encoded against the `base` one the
+  /// client acknowledged, or against nothing.
+  Snapshot {
+    tick: u32,
+    base: Option<u32>,
+    delta: Vec<u8>,
+    reliable: ReliableBlock,
+  },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SoldierSnapshot {
+  pub num: u8,
+  pub team: u8,
+  pub position: Vector2<f32>,
+  pub velocity: Vector2<f32>,
+  pub mouse_aim_x: i32,
+  pub mouse_aim_y: i32,
+  pub health: f32,
+  pub direction: i8,
+  pub dead_meat: bool,
+  /// `WeaponKind::id` of the weapon in hand.
+  pub primary_weapon: u8,
+  pub ammo_count: u8,
+  /// Animation ids and frames, enough to pose the skeleton.
+  pub legs_animation: (u8, u8),
+  pub body_animation: (u8, u8),
+}
+
+/// Bullets and grenades.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ProjectileSnapshot {
+  /// `BulletStyle` as a number.
+  pub style: u8,
+  pub owner: u8,
+  pub position: Vector2<f32>,
+  pub velocity: Vector2<f32>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ThingSnapshotKind {
+  /// `WeaponKind::id` of the weapon.
+  Weapon(u8),
+  /// `KitKind` as a number.
+  Kit(u8),
+  Flag(u8),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ThingSnapshot {
+  pub kind: ThingSnapshotKind,
+  pub position: Vector2<f32>,
+}
+
+/// What clients get to see of the world on a tick.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+  pub tick: u32,
+  pub soldiers: Vec<SoldierSnapshot>,
+  pub projectiles: Vec<ProjectileSnapshot>,
+  pub things: Vec<ThingSnapshot>,
+}
+
+fn write_vec2<W: Write>(writer: &mut W, v: Vector2<f32>) -> io::Result<()> {
+  writer.write_f32::<LittleEndian>(v.x)?;
+  writer.write_f32::<LittleEndian>(v.y)
+}
+
+fn read_vec2<R: Read>(reader: &mut R) -> io::Result<Vector2<f32>> {
+  let x = reader.read_f32::<LittleEndian>()?;
+  let y = reader.read_f32::<LittleEndian>()?;
+  Ok(Vector2::new(x, y))
+}
+
+fn invalid(what: &str) -> io::Error {
+  io::Error::new(ErrorKind::InvalidData, format!("Invalid {}", what))
+}
+
+/// Options are written as a flag byte, followed by the value if there is one.
+fn write_option_u32<W: Write>(writer: &mut W, value: Option<u32>) -> io::Result<()> {
+  writer.write_u8(value.is_some() as u8)?;
+  writer.write_u32::<LittleEndian>(value.unwrap_or(0))
+}
+
+fn read_option_u32<R: Read>(reader: &mut R) -> io::Result<Option<u32>> {
+  let some = reader.read_u8()? != 0;
+  let value = reader.read_u32::<LittleEndian>()?;
+  Ok(iif!(some, Some(value), None))
+}
+
+impl Message {
+  pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+    match *self {
+      Message::Chat { soldier, ref text } => {
+        // Cut long lines short, without splitting a character
+        let mut len = usize::min(text.len(), MAX_TEXT_LENGTH);
+
+        while !text.is_char_boundary(len) {
+          len -= 1;
+        }
+
+        writer.write_u8(0)?;
+        writer.write_u8(soldier)?;
+        writer.write_u8(len as u8)?;
+        writer.write_all(&text.as_bytes()[..len])
+      }
+      Message::Kill { killer, victim } => {
+        writer.write_u8(1)?;
+        writer.write_u8(killer.is_some() as u8)?;
+        writer.write_u8(killer.unwrap_or(0))?;
+        writer.write_u8(victim)
+      }
+      Message::Joined { soldier } => {
+        writer.write_u8(2)?;
+        writer.write_u8(soldier)
+      }
+      Message::Left { soldier } => {
+        writer.write_u8(3)?;
+        writer.write_u8(soldier)
+      }
+    }
+  }
+
+  pub fn read<R: Read>(reader: &mut R) -> io::Result<Message> {
+    match reader.read_u8()? {
+      0 => {
+        let soldier = reader.read_u8()?;
+        let mut text = vec![0u8; reader.read_u8()? as usize];
+        reader.read_exact(&mut text)?;
+        let text = String::from_utf8_lossy(&text).into_owned();
+        Ok(Message::Chat { soldier, text })
+      }
+      1 => {
+        let some = reader.read_u8()? != 0;
+        let killer = reader.read_u8()?;
+        let victim = reader.read_u8()?;
+        Ok(Message::Kill { killer: iif!(some, Some(killer), None), victim })
+      }
+      2 => Ok(Message::Joined { soldier: reader.read_u8()? }),
+      3 => Ok(Message::Left { soldier: reader.read_u8()? }),
+      _ => Err(invalid("message")),
+    }
+  }
+}
+
+impl ReliableBlock {
+  fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+    writer.write_u16::<LittleEndian>(self.ack)?;
+    writer.write_u16::<LittleEndian>(self.first)?;
+    writer.write_u8(self.messages.len() as u8)?;
+
+    for message in &self.messages {
+      message.write(writer)?;
+    }
+
+    Ok(())
+  }
+
+  fn read<R: Read>(reader: &mut R) -> io::Result<ReliableBlock> {
+    let ack = reader.read_u16::<LittleEndian>()?;
+    let first = reader.read_u16::<LittleEndian>()?;
+    let count = reader.read_u8()?;
+    let mut messages = Vec::with_capacity(count as usize);
+
+    for _ in 0..count {
+      messages.push(Message::read(reader)?);
+    }
+
+    Ok(ReliableBlock { ack, first, messages })
+  }
+}
+
+impl Packet {
+  pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+    writer.write_u32::<LittleEndian>(PROTOCOL_ID)?;
+
+    match *self {
+      Packet::ConnectRequest { version } => {
+        writer.write_u8(0)?;
+        writer.write_u16::<LittleEndian>(version)
+      }
+      Packet::ConnectAccept { soldier } => {
+        writer.write_u8(1)?;
+        writer.write_u8(soldier)
+      }
+      Packet::ConnectReject { reason } => {
+        writer.write_u8(2)?;
+        writer.write_u8(reason as u8)
+      }
+      Packet::Disconnect => writer.write_u8(3),
+      Packet::Input { tick, input, snapshot_ack, ref reliable } => {
+        writer.write_u8(4)?;
+        writer.write_u32::<LittleEndian>(tick)?;
+        input.write(writer)?;
+        write_option_u32(writer, snapshot_ack)?;
+        reliable.write(writer)
+      }
+      Packet::Snapshot { tick, base, ref delta, ref reliable } => {
+        writer.write_u8(5)?;
+        writer.write_u32::<LittleEndian>(tick)?;
+        write_option_u32(writer, base)?;
+        writer.write_u32::<LittleEndian>(delta.len() as u32)?;
+        writer.write_all(delta)?;
+        reliable.write(writer)
+      }
+    }
+  }
+
+  pub fn read<R: Read>(reader: &mut R) -> io::Result<Packet> {
+    if reader.read_u32::<LittleEndian>()? != PROTOCOL_ID {
+      return Err(invalid("protocol id"));
+    }
+
+    match reader.read_u8()? {
+      0 => Ok(Packet::ConnectRequest { version: reader.read_u16::<LittleEndian>()? }),
+      1 => Ok(Packet::ConnectAccept { soldier: reader.read_u8()? }),
+      2 => match reader.read_u8()? {
+        0 => Ok(Packet::ConnectReject { reason: RejectReason::Version }),
+        1 => Ok(Packet::ConnectReject { reason: RejectReason::Full }),
+        _ => Err(invalid("reject reason")),
+      },
+      3 => Ok(Packet::Disconnect),
+      4 => {
+        let tick = reader.read_u32::<LittleEndian>()?;
+        let input = InputFrame::read(reader)?;
+        let snapshot_ack = read_option_u32(reader)?;
+        let reliable = ReliableBlock::read(reader)?;
+        Ok(Packet::Input { tick, input, snapshot_ack, reliable })
+      }
+      5 => {
+        let tick = reader.read_u32::<LittleEndian>()?;
+        let base = read_option_u32(reader)?;
+        let len = reader.read_u32::<LittleEndian>()? as usize;
+
+        if len > MAX_PACKET_SIZE {
+          return Err(invalid("snapshot size"));
+        }
+
+        let mut delta = vec![0u8; len];
+        reader.read_exact(&mut delta)?;
+        let reliable = ReliableBlock::read(reader)?;
+        Ok(Packet::Snapshot { tick, base, delta, reliable })
+      }
+      _ => Err(invalid("packet type")),
+    }
+  }
+
+  pub fn to_bytes(&self) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    self.write(&mut bytes).expect("Writing to a Vec can't fail");
+    bytes
+  }
+
+  pub fn from_bytes(bytes: &[u8]) -> io::Result<Packet> {
+    Packet::read(&mut Cursor::new(bytes))
+  }
+}
+
+impl Snapshot {
+  pub fn capture(world: &World) -> Snapshot {
+    let parts = &world.state.soldier_parts;
+
+    let soldiers = world.soldiers
+      .iter()
+      .map(|soldier| SoldierSnapshot {
+        num: soldier.num as u8,
+        team: soldier.team,
+        position: parts.pos[soldier.num],
+        velocity: parts.velocity[soldier.num],
+        mouse_aim_x: soldier.control.mouse_aim_x,
+        mouse_aim_y: soldier.control.mouse_aim_y,
+        health: soldier.health,
+        direction: soldier.direction,
+        dead_meat: soldier.dead_meat,
+        primary_weapon: soldier.primary_weapon.kind.id() as u8,
+        ammo_count: soldier.primary_weapon.ammo_count as u8,
+        legs_animation: (soldier.legs_animation.id as u8, soldier.legs_animation.curr_frame as u8),
+        body_animation: (soldier.body_animation.id as u8, soldier.body_animation.curr_frame as u8),
+      })
+      .collect();
+
+    let bullets = world.bullets.iter().map(|bullet| ProjectileSnapshot {
+      style: bullet.style as u8,
+      owner: bullet.owner as u8,
+      position: bullet.pos,
+      velocity: bullet.velocity,
+    });
+
+    let grenades = world.grenades.iter().map(|grenade| ProjectileSnapshot {
+      style: grenade.style as u8,
+      owner: grenade.owner as u8,
+      position: grenade.pos,
+      velocity: grenade.pos - grenade.old_pos,
+    });
+
+    let things = world.things.iter().chain(world.flags.iter().map(|flag| &flag.thing));
+
+    Snapshot {
+      tick: world.tick,
+      soldiers,
+      projectiles: bullets.chain(grenades).collect(),
+      things: things
+        .map(|thing| ThingSnapshot {
+          kind: match thing.kind {
+            ThingKind::Weapon(weapon) => ThingSnapshotKind::Weapon(weapon.kind.id() as u8),
+            ThingKind::Kit(kind) => ThingSnapshotKind::Kit(kind as u8),
+            ThingKind::Flag(team) => ThingSnapshotKind::Flag(team),
+          },
+          position: thing.position(),
+        })
+        .collect(),
+    }
+  }
+
+  pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+    writer.write_u32::<LittleEndian>(self.tick)?;
+    writer.write_u8(self.soldiers.len() as u8)?;
+
+    for soldier in &self.soldiers {
+      writer.write_u8(soldier.num)?;
+      writer.write_u8(soldier.team)?;
+      write_vec2(writer, soldier.position)?;
+      write_vec2(writer, soldier.velocity)?;
+      writer.write_i32::<LittleEndian>(soldier.mouse_aim_x)?;
+      writer.write_i32::<LittleEndian>(soldier.mouse_aim_y)?;
+      writer.write_f32::<LittleEndian>(soldier.health)?;
+      writer.write_i8(soldier.direction)?;
+      writer.write_u8(soldier.dead_meat as u8)?;
+      writer.write_u8(soldier.primary_weapon)?;
+      writer.write_u8(soldier.ammo_count)?;
+      writer.write_u8(soldier.legs_animation.0)?;
+      writer.write_u8(soldier.legs_animation.1)?;
+      writer.write_u8(soldier.body_animation.0)?;
+      writer.write_u8(soldier.body_animation.1)?;
+    }
+
+    writer.write_u16::<LittleEndian>(self.projectiles.len() as u16)?;
+
+    for projectile in &self.projectiles {
+      writer.write_u8(projectile.style)?;
+      writer.write_u8(projectile.owner)?;
+      write_vec2(writer, projectile.position)?;
+      write_vec2(writer, projectile.velocity)?;
+    }
+
+    writer.write_u16::<LittleEndian>(self.things.len() as u16)?;
+
+    for thing in &self.things {
+      let (kind, id) = match thing.kind {
+        ThingSnapshotKind::Weapon(id) => (0, id),
+        ThingSnapshotKind::Kit(id) => (1, id),
+        ThingSnapshotKind::Flag(team) => (2, team),
+      };
+
+      writer.write_u8(kind)?;
+      writer.write_u8(id)?;
+      write_vec2(writer, thing.position)?;
+    }
+
+    Ok(())
+  }
+
+  pub fn read<R: Read>(reader: &mut R) -> io::Result<Snapshot> {
+    let tick = reader.read_u32::<LittleEndian>()?;
+    let count = reader.read_u8()?;
+    let mut soldiers = Vec::with_capacity(count as usize);
+
+    for _ in 0..count {
+      soldiers.push(SoldierSnapshot {
+        num: reader.read_u8()?,
+        team: reader.read_u8()?,
+        position: read_vec2(reader)?,
+        velocity: read_vec2(reader)?,
+        mouse_aim_x: reader.read_i32::<LittleEndian>()?,
+        mouse_aim_y: reader.read_i32::<LittleEndian>()?,
+        health: reader.read_f32::<LittleEndian>()?,
+        direction: reader.read_i8()?,
+        dead_meat: reader.read_u8()? != 0,
+        primary_weapon: reader.read_u8()?,
+        ammo_count: reader.read_u8()?,
+        legs_animation: (reader.read_u8()?, reader.read_u8()?),
+        body_animation: (reader.read_u8()?, reader.read_u8()?),
+      });
+    }
+
+    let count = reader.read_u16::<LittleEndian>()?;
+    let mut projectiles = Vec::with_capacity(count as usize);
+
+    for _ in 0..count {
+      projectiles.push(ProjectileSnapshot {
+        style: reader.read_u8()?,
+        owner: reader.read_u8()?,
+        position: read_vec2(reader)?,
+        velocity: read_vec2(reader)?,
+      });
+    }
+
+    let count = reader.read_u16::<LittleEndian>()?;
+    let mut things = Vec::with_capacity(count as usize);
+
+    for _ in 0..count {
+      let kind = match (reader.read_u8()?, reader.read_u8()?) {
+        (0, id) => ThingSnapshotKind::Weapon(id),
+        (1, id) => ThingSnapshotKind::Kit(id),
+        (2, team) => ThingSnapshotKind::Flag(team),
+        _ => return Err(invalid("thing")),
+      };
+
+      things.push(ThingSnapshot { kind, position: read_vec2(reader)? });
+    }
+
+    Ok(Snapshot { tick, soldiers, projectiles, things })
+  }
+
+  pub fn to_bytes(&self) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    self.write(&mut bytes).expect("Writing to a Vec can't fail");
+    bytes
+  }
+}
+
+/// Encodes `data` as the difference to `base`. The two are XORed, which leaves zeros wherever
+/// nothing changed, and the result is stored as runs of zeros followed by the bytes that did
+/// change.
+pub fn delta_encode(base: &[u8], data: &[u8]) -> Vec<u8> {
+  let diff = |i: usize| data[i] ^ base.get(i).cloned().unwrap_or(0);
+  let mut delta = Vec::new();
+  let mut i = 0;
+
+  delta.write_u32::<LittleEndian>(data.len() as u32).unwrap();
+
+  while i < data.len() {
+    let zeros = i;
+    while i < data.len() && i - zeros < 0xffff && diff(i) == 0 { i += 1; }
+
+    let changed = i;
+    while i < data.len() && i - changed < 0xffff && diff(i) != 0 { i += 1; }
+
+    delta.write_u16::<LittleEndian>((changed - zeros) as u16).unwrap();
+    delta.write_u16::<LittleEndian>((i - changed) as u16).unwrap();
+    delta.extend((changed..i).map(&diff));
+  }
+
+  delta
+}
+
+/// Undoes `delta_encode`, given the same `base`.
+pub fn delta_decode(base: &[u8], delta: &[u8]) -> io::Result<Vec<u8>> {
+  let mut reader = Cursor::new(delta);
+  let len = reader.read_u32::<LittleEndian>()? as usize;
+
+  if len > MAX_PACKET_SIZE * 4 {
+    return Err(invalid("snapshot size"));
+  }
+
+  let mut data = vec![0u8; len];
+  let mut i = 0;
+
+  while i < len {
+    i += reader.read_u16::<LittleEndian>()? as usize;
+    let changed = reader.read_u16::<LittleEndian>()? as usize;
+
+    if i + changed > len {
+      return Err(invalid("snapshot delta"));
+    }
+
+    reader.read_exact(&mut data[i..i + changed])?;
+    i += changed;
+  }
+
+  for (i, byte) in data.iter_mut().enumerate() {
+    *byte ^= base.get(i).cloned().unwrap_or(0);
+  }
+
+  Ok(data)
+}
+
+/// Sends messages until the other side acknowledges them and hands over the ones received, in
+/// order and without duplicates.
+#[derive(Debug, Default)]
+pub struct ReliableChannel {
+  unacked: VecDeque<Message>,
+  /// Sequence number of the first unacknowledged message.
+  first_unacked: u16,
+  /// Number of messages received so far, which is the sequence number of the next one.
+  received: u16,
+}
+
+impl ReliableChannel {
+  pub fn send(&mut self, message: Message) {
+    self.unacked.push_back(message);
+  }
+
+  fn block(&self) -> ReliableBlock {
+    ReliableBlock {
+      ack: self.received,
+      first: self.first_unacked,
+      messages: self.unacked.iter().take(MAX_MESSAGES_PER_PACKET).cloned().collect(),
+    }
+  }
+
+  fn receive(&mut self, block: ReliableBlock) -> Vec<Message> {
+    // Acks from packets that arrived out of order are older than what we've got, and ignored
+    let acked = block.ack.wrapping_sub(self.first_unacked) as usize;
+
+    if acked <= self.unacked.len() {
+      self.unacked.drain(..acked);
+      self.first_unacked = block.ack;
+    }
+
+    let mut messages = Vec::new();
+
+    for (i, message) in block.messages.into_iter().enumerate() {
+      if block.first.wrapping_add(i as u16) == self.received {
+        messages.push(message);
+        self.received = self.received.wrapping_add(1);
+      }
+    }
+
+    messages
+  }
+}
+
+fn send_packet(socket: &UdpSocket, addr: SocketAddr, packet: &Packet) {
+  send_bytes(socket, addr, &packet.to_bytes());
+}
+
+fn send_bytes(socket: &UdpSocket, addr: SocketAddr, bytes: &[u8]) {
+  if let Err(e) = socket.send_to(bytes, addr) {
+    println!("Failed to send packet to {}: {}", addr, e);
+  }
+}
+
+/// Receives the next valid packet, or `None` once there's nothing left to read.
+fn receive_packet(socket: &UdpSocket, buffer: &mut [u8]) -> Option<(Packet, SocketAddr)> {
+  loop {
+    let (len, addr) = match socket.recv_from(buffer) {
+      Ok(received) => received,
+      Err(ref e) if e.kind() == ErrorKind::WouldBlock => return None,
+      // Windows reports ICMP port unreachable errors from earlier sends here
+      Err(ref e) if e.kind() == ErrorKind::ConnectionReset => continue,
+      Err(e) => {
+        println!("Failed to receive packet: {}", e);
+        return None;
+      }
+    };
+
+    if let Ok(packet) = Packet::from_bytes(&buffer[..len]) {
+      return Some((packet, addr));
+    }
+  }
+}
+
+/// Keeps the newest snapshots, by tick.
+fn remember(history: &mut VecDeque<(u32, Vec<u8>)>, tick: u32, data: Vec<u8>) {
+  history.push_back((tick, data));
+
+  while history.len() > SNAPSHOT_HISTORY {
+    history.pop_front();
+  }
+}
+
+fn recall(history: &VecDeque<(u32, Vec<u8>)>, tick: u32) -> Option<&[u8]> {
+  history.iter().find(|&&(t, _)| t == tick).map(|&(_, ref data)| &data[..])
+}
+
+fn snapshot_packet(
+  tick: u32,
+  base: Option<(u32, &[u8])>,
+  data: &[u8],
+  reliable: &ReliableBlock,
+) -> Vec<u8> {
+  let packet = Packet::Snapshot {
+    tick,
+    base: base.map(|(tick, _)| tick),
+    delta: delta_encode(base.map_or(&[][..], |(_, data)| data), data),
+    reliable: reliable.clone(),
+  };
+
+  packet.to_bytes()
+}
+
+#[derive(Debug)]
+pub enum ServerEvent {
+  /// Someone with a matching protocol version wants to join, answer with `NetServer::accept`
+  /// or `NetServer::reject`.
+  ConnectRequest(SocketAddr),
+  /// The client playing the soldier left or timed out.
+  Disconnected(usize),
+  Message(usize, Message),
+}
+
+struct Connection {
+  soldier: usize,
+  input: InputFrame,
+  /// Tick of the newest input received, older ones arriving late are dropped.
+  input_tick: Option<u32>,
+  snapshot_ack: Option<u32>,
+  reliable: ReliableChannel,
+  /// Server tick the last packet from the client arrived on.
+  last_heard: u32,
+}
+
+/// The server's end of the connections. It runs on the world's ticks: `receive` at the start
+/// of one, `send_snapshots` at the end.
+pub struct NetServer {
+  socket: UdpSocket,
+  connections: HashMap<SocketAddr, Connection>,
+  history: VecDeque<(u32, Vec<u8>)>,
+  buffer: Vec<u8>,
+}
+
+impl NetServer {
+  pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<NetServer> {
+    let socket = UdpSocket::bind(addr)?;
+    socket.set_nonblocking(true)?;
+
+    Ok(NetServer {
+      socket,
+      connections: HashMap::new(),
+      history: VecDeque::new(),
+      buffer: vec![0u8; MAX_PACKET_SIZE],
+    })
+  }
+
+  pub fn local_addr(&self) -> io::Result<SocketAddr> {
+    self.socket.local_addr()
+  }
+
+  /// Handles everything that arrived since the last call and drops clients that went quiet.
+  pub fn receive(&mut self, tick: u32) -> Vec<ServerEvent> {
+    let mut events = Vec::new();
+
+    while let Some((packet, addr)) = receive_packet(&self.socket, &mut self.buffer) {
+      match packet {
+        Packet::ConnectRequest { version } => {
+          if version != PROTOCOL_VERSION {
+            println!("Rejecting {}, protocol version {} instead of {}", addr, version, PROTOCOL_VERSION);
+            self.reject(addr, RejectReason::Version);
+          } else if let Some(connection) = self.connections.get(&addr) {
+            // The accept got lost on the way
+            let accept = Packet::ConnectAccept { soldier: connection.soldier as u8 };
+            send_packet(&self.socket, addr, &accept);
+          } else {
+            events.push(ServerEvent::ConnectRequest(addr));
+          }
+        }
+        Packet::Disconnect => {
+          if let Some(connection) = self.connections.remove(&addr) {
+            events.push(ServerEvent::Disconnected(connection.soldier));
+          }
+        }
+        Packet::Input { tick: input_tick, input, snapshot_ack, reliable } => {
+          let connection = match self.connections.get_mut(&addr) {
+            Some(connection) => connection,
+            None => continue,
+          };
+
+          connection.last_heard = tick;
+
+          if connection.input_tick.map_or(true, |last| input_tick > last) {
+            connection.input_tick = Some(input_tick);
+            connection.input = input;
+          }
+
+          if snapshot_ack > connection.snapshot_ack {
+            connection.snapshot_ack = snapshot_ack;
+          }
+
+          for message in connection.reliable.receive(reliable) {
+            events.push(ServerEvent::Message(connection.soldier, message));
+          }
+        }
+        _ => (),
+      }
+    }
+
+    let timed_out: Vec<SocketAddr> = self.connections
+      .iter()
+      .filter(|&(_, connection)| tick.wrapping_sub(connection.last_heard) > CONNECTION_TIMEOUT)
+      .map(|(&addr, _)| addr)
+      .collect();
+
+    for addr in timed_out {
+      if let Some(connection) = self.connections.remove(&addr) {
+        events.push(ServerEvent::Disconnected(connection.soldier));
+      }
+    }
+
+    events
+  }
+
+  pub fn accept(&mut self, addr: SocketAddr, soldier: usize, tick: u32) {
+    self.connections.insert(addr, Connection {
+      soldier,
+      input: InputFrame::default(),
+      input_tick: None,
+      snapshot_ack: None,
+      reliable: ReliableChannel::default(),
+      last_heard: tick,
+    });
+
+    send_packet(&self.socket, addr, &Packet::ConnectAccept { soldier: soldier as u8 });
+  }
+
+  pub fn reject(&mut self, addr: SocketAddr, reason: RejectReason) {
+    send_packet(&self.socket, addr, &Packet::ConnectReject { reason });
+  }
+
+  /// Adds the latest input of every client to `inputs`, keyed by soldier.
+  pub fn inputs(&self, inputs: &mut HashMap<usize, InputFrame>) {
+    for connection in self.connections.values() {
+      inputs.insert(connection.soldier, connection.input);
+    }
+  }
+
+  pub fn broadcast(&mut self, message: &Message) {
+    for connection in self.connections.values_mut() {
+      connection.reliable.send(message.clone());
+    }
+  }
+
+  pub fn send(&mut self, soldier: usize, message: Message) {
+    if let Some(connection) = self.connections.values_mut().find(|c| c.soldier == soldier) {
+      connection.reliable.send(message);
+    }
+  }
+
+  /// Sends every client the world as it is now, as a delta to the newest snapshot it has
+  /// acknowledged if that's still around.
+  pub fn send_snapshots(&mut self, world: &World) {
+    self.send_snapshot(&Snapshot::capture(world));
+  }
+
+  fn send_snapshot(&mut self, snapshot: &Snapshot) {
+    let data = snapshot.to_bytes();
+
+    for (&addr, connection) in &self.connections {
+      let base = connection.snapshot_ack.and_then(|tick| {
+        recall(&self.history, tick).map(|data| (tick, data))
+      });
+
+      let reliable = connection.reliable.block();
+      let mut packet = snapshot_packet(snapshot.tick, base, &data, &reliable);
+
+      // A delta can come out bigger than the snapshot itself when most of it changed
+      if packet.len() > MAX_PACKET_SIZE && base.is_some() {
+        packet = snapshot_packet(snapshot.tick, None, &data, &reliable);
+      }
+
+      // The client couldn't receive it, so it keeps the last snapshot that fit instead
+      if packet.len() > MAX_PACKET_SIZE {
+        println!("Snapshot of {} bytes is too big to send to {}", data.len(), addr);
+        continue;
+      }
+
+      send_bytes(&self.socket, addr, &packet);
+    }
+
+    remember(&mut self.history, snapshot.tick, data);
+  }
+}
+
+#[derive(Debug)]
+pub enum ClientEvent {
+  /// The server let us in, playing the given soldier.
+  Connected(usize),
+  Rejected(RejectReason),
+  /// A snapshot newer than any before it.
+  Snapshot(Snapshot),
+  Message(Message),
+}
+
+/// The client's end of the connection.
+pub struct NetClient {
+  socket: UdpSocket,
+  server: SocketAddr,
+  /// The soldier the client plays, once connected.
+  pub soldier: Option<usize>,
+  history: VecDeque<(u32, Vec<u8>)>,
+  /// Tick of the newest snapshot received.
+  latest: Option<u32>,
+  reliable: ReliableChannel,
+}
+
+impl NetClient {
+  /// Starts connecting to the server, `send_input` keeps asking until it answers.
+  pub fn connect(server: SocketAddr) -> io::Result<NetClient> {
+    let local: SocketAddr = if server.is_ipv4() {
+      ([0u8; 4], 0).into()
+    } else {
+      ([0u16; 8], 0).into()
+    };
+
+    let socket = UdpSocket::bind(local)?;
+    socket.set_nonblocking(true)?;
+    send_packet(&socket, server, &Packet::ConnectRequest { version: PROTOCOL_VERSION });
+
+    Ok(NetClient {
+      socket,
+      server,
+      soldier: None,
+      history: VecDeque::new(),
+      latest: None,
+      reliable: ReliableChannel::default(),
+    })
+  }
+
+  pub fn receive(&mut self) -> Vec<ClientEvent> {
+    let mut events = Vec::new();
+    let mut buffer = [0u8; MAX_PACKET_SIZE];
+
+    while let Some((packet, addr)) = receive_packet(&self.socket, &mut buffer) {
+      if addr != self.server {
+        continue;
+      }
+
+      match packet {
+        Packet::ConnectAccept { soldier } => {
+          if self.soldier.is_none() {
+            self.soldier = Some(soldier as usize);
+            events.push(ClientEvent::Connected(soldier as usize));
+          }
+        }
+        Packet::ConnectReject { reason } => events.push(ClientEvent::Rejected(reason)),
+        Packet::Snapshot { tick, base, delta, reliable } => {
+          if self.soldier.is_none() {
+            continue;
+          }
+
+          for message in self.reliable.receive(reliable) {
+            events.push(ClientEvent::Message(message));
+          }
+
+          let data = match base {
+            None => delta_decode(&[], &delta),
+            Some(base) => match recall(&self.history, base) {
+              Some(base) => delta_decode(base, &delta),
+              None => continue,
+            },
+          };
+
+          let snapshot = match data.as_ref().map(|data| Snapshot::read(&mut Cursor::new(data))) {
+            Ok(Ok(snapshot)) => snapshot,
+            _ => continue,
+          };
+
+          if self.latest.map_or(true, |latest| tick > latest) {
+            self.latest = Some(tick);
+            events.push(ClientEvent::Snapshot(snapshot));
+          }
+
+          if let Ok(data) = data {
+            remember(&mut self.history, tick, data);
+          }
+        }
+        _ => (),
+      }
+    }
+
+    events
+  }
+
+  /// Sends the input for a tick, or asks to join again while the server hasn't answered.
+  pub fn send_input(&mut self, tick: u32, input: InputFrame) {
+    let packet = match self.soldier {
+      None => Packet::ConnectRequest { version: PROTOCOL_VERSION },
+      Some(_) => Packet::Input {
+        tick,
+        input,
+        snapshot_ack: self.latest,
+        reliable: self.reliable.block(),
+      },
+    };
+
+    send_packet(&self.socket, self.server, &packet);
+  }
+
+  pub fn send(&mut self, message: Message) {
+    self.reliable.send(message);
+  }
+
+  pub fn disconnect(&mut self) {
+    send_packet(&self.socket, self.server, &Packet::Disconnect);
+    self.soldier = None;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::thread;
+  use std::time::{Duration, Instant};
+
+  fn snapshot(tick: u32, x: f32) -> Snapshot {
+    Snapshot {
+      tick,
+      soldiers: vec![SoldierSnapshot {
+        num: 3,
+        team: 1,
+        position: Vector2::new(x, 100.0),
+        velocity: Vector2::new(1.0, 0.0),
+        mouse_aim_x: 10,
+        mouse_aim_y: -20,
+        health: 150.0,
+        direction: 1,
+        dead_meat: false,
+        primary_weapon: 0,
+        ammo_count: 30,
+        legs_animation: (1, 2),
+        body_animation: (3, 4),
+      }],
+      projectiles: vec![ProjectileSnapshot {
+        style: 1,
+        owner: 3,
+        position: Vector2::new(x + 10.0, 90.0),
+        velocity: Vector2::new(20.0, 0.0),
+      }],
+      things: vec![ThingSnapshot {
+        kind: ThingSnapshotKind::Flag(1),
+        position: Vector2::new(500.0, 200.0),
+      }],
+    }
+  }
+
+  /// Keeps calling `done` until it returns true. Packets take a moment even on loopback, and
+  /// longer on a busy machine, so this only gives up after a generous deadline.
+  fn wait_until<F: FnMut() -> bool>(mut done: F) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(10);
+
+    while Instant::now() < deadline {
+      if done() {
+        return true;
+      }
+
+      thread::sleep(Duration::from_millis(1));
+    }
+
+    false
+  }
+
+  /// Collects what `receive` comes up with until there are at least `count` items.
+  fn gather<T, F: FnMut() -> Vec<T>>(count: usize, mut receive: F) -> Vec<T> {
+    let mut items = Vec::new();
+
+    wait_until(|| {
+      items.extend(receive());
+      items.len() >= count
+    });
+
+    items
+  }
+
+  /// Sorts the client's events into snapshots and messages.
+  fn split(events: Vec<ClientEvent>) -> (Vec<Snapshot>, Vec<Message>) {
+    let (mut snapshots, mut messages) = (Vec::new(), Vec::new());
+
+    for event in events {
+      match event {
+        ClientEvent::Snapshot(snapshot) => snapshots.push(snapshot),
+        ClientEvent::Message(message) => messages.push(message),
+        _ => (),
+      }
+    }
+
+    (snapshots, messages)
+  }
+
+  /// A server with a client connected to it, playing soldier 3.
+  fn connect() -> (NetServer, NetClient) {
+    let mut server = NetServer::bind("127.0.0.1:0").unwrap();
+    let mut client = NetClient::connect(server.local_addr().unwrap()).unwrap();
+
+    let addr = match gather(1, || server.receive(0)).first() {
+      Some(&ServerEvent::ConnectRequest(addr)) => addr,
+      other => panic!("expected a connect request, got {:?}", other),
+    };
+
+    server.accept(addr, 3, 0);
+
+    match gather(1, || client.receive()).first() {
+      Some(&ClientEvent::Connected(3)) => (),
+      other => panic!("expected to be let in, got {:?}", other),
+    }
+
+    (server, client)
+  }
+
+  /// Sends the client's ack for the newest snapshot and waits for the server to take it in.
+  fn ack(server: &mut NetServer, client: &mut NetClient, tick: u32) {
+    client.send_input(tick, InputFrame::default());
+
+    let acked = wait_until(|| {
+      server.receive(tick);
+      server.connections.values().all(|c| c.snapshot_ack == Some(tick))
+    });
+
+    assert!(acked);
+  }
+
+  #[test]
+  fn rejects_other_protocol_versions() {
+    let mut server = NetServer::bind("127.0.0.1:0").unwrap();
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket.set_nonblocking(true).unwrap();
+
+    let request = Packet::ConnectRequest { version: PROTOCOL_VERSION + 1 };
+    send_packet(&socket, server.local_addr().unwrap(), &request);
+
+    let mut buffer = [0u8; MAX_PACKET_SIZE];
+    let replies = gather(1, || {
+      assert!(server.receive(0).is_empty());
+      receive_packet(&socket, &mut buffer).into_iter().collect()
+    });
+
+    match replies.first() {
+      Some(&(Packet::ConnectReject { reason }, _)) => assert_eq!(reason, RejectReason::Version),
+      other => panic!("expected a rejection, got {:?}", other),
+    }
+
+    assert!(server.connections.is_empty());
+  }
+
+  #[test]
+  fn snapshots_are_sent_as_deltas_to_acknowledged_ones() {
+    let (mut server, mut client) = connect();
+
+    // Nothing acknowledged yet, so the first one goes out whole
+    server.send_snapshot(&snapshot(1, 0.0));
+    assert_eq!(gather(1, || split(client.receive()).0), vec![snapshot(1, 0.0)]);
+
+    ack(&mut server, &mut client, 1);
+    server.send_snapshot(&snapshot(2, 5.0));
+    assert_eq!(gather(1, || split(client.receive()).0), vec![snapshot(2, 5.0)]);
+
+    // Without the acknowledged base the delta can't be decoded. The message riding along is
+    // handed over regardless, which tells the packet did arrive.
+    ack(&mut server, &mut client, 2);
+    client.history.clear();
+    server.broadcast(&Message::Joined { soldier: 4 });
+    server.send_snapshot(&snapshot(3, 10.0));
+
+    let messages = gather(1, || split(client.receive()).1);
+    assert_eq!(messages, vec![Message::Joined { soldier: 4 }]);
+    assert_eq!(client.latest, Some(2));
+  }
+
+  #[test]
+  fn reliable_messages_arrive_in_order_exactly_once() {
+    let (mut server, mut client) = connect();
+
+    let sent = vec![
+      Message::Chat { soldier: 3, text: "gg".to_owned() },
+      Message::Kill { killer: Some(3), victim: 5 },
+      Message::Kill { killer: None, victim: 3 },
+    ];
+
+    for message in &sent {
+      server.broadcast(message);
+    }
+
+    // Every snapshot carries the messages until the client acknowledges them. The messages of a
+    // packet are handed over before its snapshot, so once all three snapshots are in, so is
+    // every copy of the messages.
+    for tick in 1..4 {
+      server.send_snapshot(&snapshot(tick, 0.0));
+    }
+
+    let (snapshots, messages) = split(gather(6, || client.receive()));
+    assert_eq!(snapshots.len(), 3);
+    assert_eq!(messages, sent);
+
+    client.send(Message::Chat { soldier: 3, text: "wp".to_owned() });
+
+    for tick in 1..4 {
+      client.send_input(tick, InputFrame::default());
+    }
+
+    let mut events = Vec::new();
+
+    let received = wait_until(|| {
+      events.extend(server.receive(1));
+      server.connections.values().all(|c| c.input_tick == Some(3))
+    });
+
+    assert!(received);
+    assert_eq!(events.len(), 1);
+
+    match events[0] {
+      ServerEvent::Message(3, Message::Chat { ref text, .. }) => assert_eq!(text, "wp"),
+      ref other => panic!("expected the chat line, got {:?}", other),
+    }
+
+    // The inputs acknowledged the messages, so the next snapshot comes without them
+    assert!(server.connections.values().all(|c| c.reliable.unacked.is_empty()));
+    server.send_snapshot(&snapshot(4, 0.0));
+    let (snapshots, messages) = split(gather(1, || client.receive()));
+    assert_eq!(snapshots, vec![snapshot(4, 0.0)]);
+    assert!(messages.is_empty());
+  }
+
+  #[test]
+  fn delta_round_trips() {
+    let base: Vec<u8> = (0..1000).map(|i| (i * 7 % 251) as u8).collect();
+    let mut data = base.clone();
+    data[10] ^= 0xff;
+
+    for byte in &mut data[500..520] {
+      *byte = byte.wrapping_add(1);
+    }
+
+    data.extend(&[1, 2, 3]);
+
+    let cases: &[(&[u8], &[u8])] = &[
+      (&base[..], &data[..]),
+      (&data[..], &base[..]),
+      (&[], &data[..]),
+      (&data[..], &[]),
+      (&data[..], &data[..]),
+    ];
+
+    for &(base, data) in cases {
+      assert_eq!(delta_decode(base, &delta_encode(base, data)).unwrap(), data);
+    }
+  }
+
+  #[test]
+  fn unchanged_data_encodes_to_a_single_run() {
+    let data: Vec<u8> = (0..1000).map(|i| (i % 256) as u8).collect();
+    assert_eq!(delta_encode(&data, &data).len(), 8);
+  }
+
+  #[test]
+  fn runs_longer_than_a_u16_are_split() {
+    let data = vec![0xaa; MAX_PACKET_SIZE * 4];
+
+    assert_eq!(delta_decode(&[], &delta_encode(&[], &data)).unwrap(), data);
+    assert_eq!(delta_decode(&data, &delta_encode(&data, &data)).unwrap(), data);
+  }
+
+  #[test]
+  fn corrupt_deltas_are_rejected() {
+    let delta = delta_encode(&[], &[1, 2, 3]);
+
+    // More changed bytes than there are in the snapshot
+    let mut overlong = delta.clone();
+    overlong[6] = 9;
+    assert!(delta_decode(&[], &overlong).is_err());
+
+    assert!(delta_decode(&[], &delta[..delta.len() - 1]).is_err());
+
+    let mut huge: Vec<u8> = Vec::new();
+    huge.write_u32::<LittleEndian>(u32::max_value()).unwrap();
+    assert!(delta_decode(&[], &huge).is_err());
+  }
+
+  #[test]
+  fn reliable_channel_survives_sequence_wraparound() {
+    let mut sender = ReliableChannel::default();
+    let mut receiver = ReliableChannel::default();
+    sender.first_unacked = u16::max_value() - 2;
+    receiver.received = u16::max_value() - 2;
+
+    let sent: Vec<Message> = (0..6).map(|soldier| Message::Joined { soldier }).collect();
+
+    for message in &sent {
+      sender.send(message.clone());
+    }
+
+    let block = sender.block();
+    assert_eq!(receiver.receive(block.clone()), sent);
+
+    // Resent before the ack made it back
+    assert!(receiver.receive(block).is_empty());
+
+    let ack = receiver.block();
+    assert!(sender.receive(ack.clone()).is_empty());
+    assert!(sender.unacked.is_empty());
+    assert_eq!(sender.first_unacked, 3);
+
+    // An older ack arriving late changes nothing
+    let mut stale = ack;
+    stale.ack = u16::max_value() - 2;
+    sender.receive(stale);
+    assert_eq!(sender.first_unacked, 3);
+  }
+}
@@ -37,6 +37,12 @@ pub struct ParticleSystem {
   pub constraints: [Constraint; 560],
 }
 
+impl Default for ParticleSystem {
+  fn default() -> ParticleSystem {
+    ParticleSystem::new()
+  }
+}
+
 impl ParticleSystem {
   pub fn new() -> ParticleSystem {
     let active: [bool; 560] = [false; 560];
@@ -109,7 +115,7 @@ impl ParticleSystem {
 
   pub fn verlet(&mut self, i: i32) {
     // Accumulate Forces
-    let temp_pos = Vector2::new(0.0f32, 0.0f32);
+    let temp_pos = self.pos[i as usize];
     self.forces[i as usize].y += self.gravity;
 
     let mut s1: Vector2<f32> = self.pos[i as usize] * (1.00 + self.v_damping);
@@ -118,7 +124,7 @@ impl ParticleSystem {
     let d = s1 - s2;
 
     s1 = self.forces[i as usize] * self.one_over_mass[i as usize];
-    s2 = s1 * self.timestep.exp2();
+    s2 = s1 * self.timestep.powi(2);
 
     self.pos[i as usize] = d + s2;
     self.old_pos[i as usize] = temp_pos;
@@ -128,42 +134,29 @@ impl ParticleSystem {
   }
   pub fn satisfy_contstraints(&mut self) {
     if self.constraint_count > 0 {
-      for i in 1..self.constraint_count + 1 {
+      for i in 1..=self.constraint_count {
         if self.constraints[i as usize].active {
-          let mut diff = 0.0;
-          let delta = Vector2::new(self.constraints[i as usize].part_b as f32, 0.0f32)
-            - Vector2::new(self.constraints[i as usize].part_b as f32, 0.0f32);
-          let delta_length: f32 = (delta.x * delta.x + delta.x * delta.y).sqrt();
-          if delta_length != 0.0 {
-            diff = (delta_length - self.constraints[i as usize].rest_length) / delta_length;
-          }
-          if self.one_over_mass[self.constraints[i as usize].part_a as usize] > 0.0 {
-            let d = delta * (0.5 * diff);
-            self.pos[self.constraints[i as usize].part_a as usize] += d;
-          }
-          if self.one_over_mass[self.constraints[i as usize].part_b as usize] > 0.0 {
-            let d = delta * (0.5 * diff);
-            self.pos[self.constraints[i as usize].part_a as usize] += d;
-          }
+          self.satisfy_contstraints_for(i);
         }
       }
     }
   }
   pub fn satisfy_contstraints_for(&mut self, i: i32) {
+    let part_a = self.constraints[i as usize].part_a as usize;
+    let part_b = self.constraints[i as usize].part_b as usize;
     let mut diff = 0.0;
-    let delta = Vector2::new(self.constraints[i as usize].part_b as f32, 0.0f32)
-      - Vector2::new(self.constraints[i as usize].part_b as f32, 0.0f32);
+    let delta = self.pos[part_b] - self.pos[part_a];
     let delta_length: f32 = (delta.x * delta.x + delta.y * delta.y).sqrt();
     if delta_length != 0.0 {
       diff = (delta_length - self.constraints[i as usize].rest_length) / delta_length;
     }
-    if self.one_over_mass[self.constraints[i as usize].part_a as usize] > 0.0 {
+    if self.one_over_mass[part_a] > 0.0 {
       let d = delta * (0.5 * diff);
-      self.pos[self.constraints[i as usize].part_a as usize] += d;
+      self.pos[part_a] += d;
     }
-    if self.one_over_mass[self.constraints[i as usize].part_b as usize] > 0.0 {
+    if self.one_over_mass[part_b] > 0.0 {
       let d = delta * (0.5 * diff);
-      self.pos[self.constraints[i as usize].part_a as usize] += d;
+      self.pos[part_b] -= d;
     }
   }
   pub fn create_part(&mut self, start: Vector2<f32>, vel: Vector2<f32>, mass: f32, num: i32) {
@@ -0,0 +1,201 @@
+use ini::Ini;
+use rand::{SeedableRng, XorShiftRng};
+use std::collections::HashMap;
+use std::str::FromStr;
+use shared::weapons::WeaponKind;
+use shared::gamemode::GameModeKind;
+
+const SECOND: i32 = 60;
+
+/// How soldiers react to hitting the ground fast. Speeds are the soldier's vertical velocity at
+/// the moment it lands.
+#[derive(Debug, Copy, Clone)]
+pub struct FallDamage {
+  /// Landing faster than this makes the soldier stumble.
+  pub hard_landing_speed: f32,
+  /// Landing faster than this hurts.
+  pub damage_speed: f32,
+  /// Health lost per unit of speed over `damage_speed`, zero for no fall damage.
+  pub damage: f32,
+}
+
+impl FallDamage {
+  pub fn new(realistic_mode: bool) -> FallDamage {
+    if realistic_mode {
+      FallDamage {
+        hard_landing_speed: 3.0,
+        damage_speed: 3.35,
+        damage: 15.0,
+      }
+    } else {
+      FallDamage {
+        hard_landing_speed: 4.0,
+        damage_speed: 5.0,
+        damage: 20.0,
+      }
+    }
+  }
+
+  fn load(&mut self, data: &HashMap<String, String>) {
+    if let Some(value) = data.get("HardLandingSpeed") {
+      self.hard_landing_speed = f32::from_str(value).unwrap_or(self.hard_landing_speed);
+    }
+
+    if let Some(value) = data.get("FallDamageSpeed") {
+      self.damage_speed = f32::from_str(value).unwrap_or(self.damage_speed);
+    }
+
+    if let Some(value) = data.get("FallDamage") {
+      self.damage = f32::from_str(value).unwrap_or(self.damage);
+    }
+  }
+}
+
+pub struct GameSettings {
+  pub game_mode: GameModeKind,
+  /// Realistic mode: less health, weapon recoil, harder falls and soldiers only being visible while
+  /// an enemy has them in sight.
+  pub realistic_mode: bool,
+  pub fall_damage: FallDamage,
+  /// Points (or captures) a player or team needs to win the round, zero for no limit.
+  pub score_limit: i32,
+  /// Ticks a round lasts at most, zero for no limit.
+  pub time_limit: i32,
+  /// Ticks a dead soldier waits before respawning.
+  pub respawn_time: i32,
+  /// Seed for the game's random number generator.
+  pub seed: u32,
+  /// Weapons soldiers (re)spawn with.
+  pub primary_weapon: WeaponKind,
+  pub secondary_weapon: WeaponKind,
+  /// Frag grenades soldiers (re)spawn with.
+  pub grenades: i32,
+  /// Whether bullets hurt teammates.
+  pub friendly_fire: bool,
+  /// Whether bonus kits (vest, cluster, flamer, berserker, predator) appear.
+  pub bonuses: bool,
+  /// Ticks a picked up kit takes to reappear.
+  pub kit_respawn_time: i32,
+  /// Number of bots joining the game.
+  pub bots: i32,
+  /// Ticks bots take to start shooting at an enemy they've just seen.
+  pub bot_reaction_time: i32,
+  /// How well bots aim, from 0 to 1.
+  pub bot_accuracy: f32,
+}
+
+impl Default for GameSettings {
+  fn default() -> GameSettings {
+    GameSettings {
+      game_mode: GameModeKind::Deathmatch,
+      realistic_mode: false,
+      fall_damage: FallDamage::new(false),
+      score_limit: 10,
+      time_limit: 15 * 60 * SECOND,
+      respawn_time: 3 * SECOND,
+      seed: 0x50_1d_a7,
+      primary_weapon: WeaponKind::Ak74,
+      secondary_weapon: WeaponKind::Socom,
+      grenades: 3,
+      friendly_fire: false,
+      bonuses: true,
+      kit_respawn_time: 15 * SECOND,
+      bots: 0,
+      bot_reaction_time: SECOND / 3,
+      bot_accuracy: 0.7,
+    }
+  }
+}
+
+impl GameSettings {
+  /// Loads settings from the `[GAME]` section of an ini file, falling back to the defaults for
+  /// anything missing. Times are given in seconds.
+  pub fn load_from_file(path: &str) -> GameSettings {
+    let mut settings = GameSettings::default();
+
+    if let Ok(cfg) = Ini::load_from_file(path) {
+      if let Some(data) = cfg.section(Some("GAME".to_owned())) {
+        let seconds = |key: &str, default: i32| match data.get(key) {
+          Some(value) => f32::from_str(value).map(|s| (s * SECOND as f32) as i32).unwrap_or(default),
+          None => default,
+        };
+
+        if let Some(value) = data.get("GameMode") {
+          match GameModeKind::from_name(value) {
+            Some(game_mode) => settings.game_mode = game_mode,
+            None => println!("Unknown game mode: {}", value),
+          }
+        }
+
+        if let Some(value) = data.get("Realistic") {
+          settings.realistic_mode = value == "1" || value.eq_ignore_ascii_case("true");
+        }
+
+        if let Some(value) = data.get("ScoreLimit") {
+          settings.score_limit = i32::from_str(value).unwrap_or(settings.score_limit);
+        }
+
+        settings.time_limit = seconds("TimeLimit", settings.time_limit);
+        settings.respawn_time = seconds("RespawnTime", settings.respawn_time);
+        settings.kit_respawn_time = seconds("KitRespawnTime", settings.kit_respawn_time);
+        settings.bot_reaction_time = seconds("BotReactionTime", settings.bot_reaction_time);
+
+        if let Some(value) = data.get("Bots") {
+          settings.bots = i32::from_str(value).unwrap_or(settings.bots);
+        }
+
+        if let Some(value) = data.get("BotAccuracy") {
+          let accuracy = f32::from_str(value).unwrap_or(settings.bot_accuracy);
+          settings.bot_accuracy = accuracy.max(0.0).min(1.0);
+        }
+
+        if let Some(value) = data.get("Seed") {
+          settings.seed = u32::from_str(value).unwrap_or(settings.seed);
+        }
+
+        if let Some(value) = data.get("PrimaryWeapon") {
+          match WeaponKind::from_name(value) {
+            Some(weapon) => settings.primary_weapon = weapon,
+            None => println!("Unknown primary weapon: {}", value),
+          }
+        }
+
+        if let Some(value) = data.get("SecondaryWeapon") {
+          match WeaponKind::from_name(value) {
+            Some(weapon) => settings.secondary_weapon = weapon,
+            None => println!("Unknown secondary weapon: {}", value),
+          }
+        }
+
+        if let Some(value) = data.get("Grenades") {
+          settings.grenades = i32::from_str(value).unwrap_or(settings.grenades);
+        }
+
+        if let Some(value) = data.get("FriendlyFire") {
+          settings.friendly_fire = value == "1" || value.eq_ignore_ascii_case("true");
+        }
+
+        if let Some(value) = data.get("Bonuses") {
+          settings.bonuses = value == "1" || value.eq_ignore_ascii_case("true");
+        }
+      }
+
+      // Fall damage can be tuned for every game mode in [GAME], and for each one in its own
+      // section named after it, like [CTF]
+      settings.fall_damage = FallDamage::new(settings.realistic_mode);
+
+      for section in &["GAME", settings.game_mode.name()] {
+        if let Some(data) = cfg.section(Some(section.to_string())) {
+          settings.fall_damage.load(data);
+        }
+      }
+    }
+
+    settings
+  }
+  /// Creates the game's random number generator. The seed is mixed with constants because
+  /// xorshift can't start from an all-zero state.
+  pub fn rng(&self) -> XorShiftRng {
+    XorShiftRng::from_seed([self.seed, self.seed ^ 0x9e37_79b9, 0x243f_6a88, 0xb7e1_5162])
+  }
+}
@@ -1,17 +1,27 @@
 use na::Vector2;
+use rand::Rng;
 
 use shared::anims;
 use shared::parts;
-use shared::state::MainState;
+use shared::state::{MainState, GRAV};
 use shared::anims::Animation;
 use shared::parts::ParticleSystem;
 use shared::calc;
-use shared::control::Control;
+use shared::control::{Control, CIGAR_POCKET};
+use shared::input::InputFrame;
 use shared::mapfile::PolyType;
-use glutin;
+use shared::damage::{Damage, DamageSource, BodyPart, DeathType};
+use shared::events::GameEvent;
+use shared::weapons::{Weapon, WeaponKind, WeaponStats, BulletStyle};
+use shared::bullet::BulletParams;
+use shared::emitter::EmitterItem;
+use shared::spark::{SparkKind, SparkParams};
+use shared::thing::{ThingKind, ThingParams};
+use shared::kit::{self, Bonus, KitKind};
+use shared::settings::GameSettings;
+use shared::appearance::{Headgear, PlayerAppearance};
 
 const SLIDELIMIT: f32 = 0.2;
-const GRAV: f32 = 0.06;
 const SURFACECOEFX: f32 = 0.970;
 const SURFACECOEFY: f32 = 0.970;
 const CROUCHMOVESURFACECOEFX: f32 = 0.85;
@@ -26,12 +36,64 @@ const POS_PRONE: u8 = 3;
 const MAX_VELOCITY: f32 = 11.0;
 const SOLDIER_COL_RADIUS: f32 = 3.0;
 
+pub const START_HEALTH: f32 = 150.0;
+pub const REALISTIC_HEALTH: f32 = 65.0;
+const HEADCHOP_DEATH_HEALTH: f32 = -90.0;
+const BRUTAL_DEATH_HEALTH: f32 = -400.0;
+const VEST_ABSORPTION: f32 = 0.67;
+const HALF_DEAD_TIME: i32 = 30;
+const HURTS_DAMAGE: f32 = 0.3;
+
+const SHOTGUN_PELLETS: i32 = 6;
+const BINK_SPREAD: f32 = 0.001;
+const MAX_BINK: i32 = 60;
+/// Radians the aim is pushed up per point of a weapon's recoil, in realistic mode.
+const RECOIL_ANGLE: f32 = 0.002;
+const MAX_RECOIL: f32 = 0.6;
+const RECOIL_DECAY: f32 = 0.9;
+
+/// Ticks a soldier stays visible after the last enemy lost sight of it, in realistic mode.
+pub const VISIBLE_TIME: u8 = 45;
+/// Ticks a soldier keeps burning after catching fire.
+const BURN_TIME: u8 = 180;
+/// Burning soldiers take `BURN_DAMAGE` every `BURN_INTERVAL` ticks.
+const BURN_INTERVAL: u8 = 10;
+const BURN_DAMAGE: f32 = 3.0;
+/// Ticks between the flames thrown off by a burning soldier.
+const FIRE_SPARK_INTERVAL: u8 = 3;
+
+/// Ticks a soldier spends crouched after a hard landing.
+const HARD_LANDING_TIME: i32 = 24;
+const HARD_LANDING_BINK: i32 = 20;
+
+/// Ticks the grenade key has to be held for a full power throw.
+pub const GRENADE_MAX_CHARGE: i32 = 60;
+const GRENADE_MIN_POWER: f32 = 0.5;
+const GRENADE_MAX_POWER: f32 = 2.0;
+const WEAPON_THROW_SPEED: f32 = 6.0;
+
+const MAX_GRENADES: i32 = 5;
+const CLUSTER_KIT_GRENADES: i32 = 3;
+const MAX_VEST: f32 = 100.0;
+
+const HEAD: usize = 12;
+const LEFT_ELBOW: usize = 13;
+const RIGHT_ELBOW: usize = 14;
+const RIGHT_HAND: usize = 15;
+/// Health soldiers (re)spawn with, and the most a medikit heals them up to.
+pub fn max_health(settings: &GameSettings) -> f32 {
+  iif!(settings.realistic_mode, REALISTIC_HEALTH, START_HEALTH)
+}
+
 #[allow(dead_code)]
 pub struct Soldier {
   pub active: bool,
   pub dead_meat: bool,
   pub style: u8,
   pub num: usize,
+  pub team: u8,
+  pub appearance: PlayerAppearance,
+  /// Ticks until the soldier fades out of sight, see `VISIBLE_TIME`.
   pub visible: u8,
   pub on_ground: bool,
   pub on_ground_for_law: bool,
@@ -49,9 +111,32 @@ pub struct Soldier {
   pub idle_time: i32,
   pub idle_random: i8,
   pub position: u8,
+  /// Ticks left until the soldier stops burning.
   pub on_fire: u8,
+  /// Who set the soldier on fire, and gets the kill if it burns to death.
+  pub igniter: Option<usize>,
   pub collider_distance: u8,
   pub half_dead: bool,
+  pub half_dead_time: i32,
+  pub respawn_counter: i32,
+  pub last_hit: Option<Damage>,
+  pub primary_weapon: Weapon,
+  pub secondary_weapon: Weapon,
+  pub tertiary_weapon: Weapon,
+  /// Weapons the soldier (re)spawns with.
+  pub loadout_primary: WeaponKind,
+  pub loadout_secondary: WeaponKind,
+  pub fired: bool,
+  /// Aim disturbance from being hit or from the recoil of the own weapon, in ticks.
+  pub bink: i32,
+  /// Angle the aim is pushed up by the weapon's recoil, in realistic mode.
+  pub recoil: f32,
+  /// Ticks the grenade being thrown has been charged for, 0 if not throwing one.
+  pub grenade_charge: i32,
+  /// Ticks left until the soldier gets back up from a hard landing.
+  pub landing_time: i32,
+  pub bonus: Option<Bonus>,
+  pub bonus_time: i32,
   pub skeleton: parts::ParticleSystem,
   pub legs_animation: Box<anims::Animation>,
   pub body_animation: Box<anims::Animation>,
@@ -59,88 +144,88 @@ pub struct Soldier {
 }
 
 impl Soldier {
-  pub fn update_keys(&mut self, input: &glutin::KeyboardInput) {
-    match input.state {
-      glutin::ElementState::Pressed => match input.virtual_keycode {
-        Some(glutin::VirtualKeyCode::A) => self.control.left = true,
-        Some(glutin::VirtualKeyCode::D) => self.control.right = true,
-        Some(glutin::VirtualKeyCode::W) => self.control.up = true,
-        Some(glutin::VirtualKeyCode::S) => self.control.down = true,
-        Some(glutin::VirtualKeyCode::Q) => self.control.change = true,
-        Some(glutin::VirtualKeyCode::E) => self.control.throw = true,
-        Some(glutin::VirtualKeyCode::X) => self.control.prone = true,
-        _ => {}
-      },
-      glutin::ElementState::Released => match input.virtual_keycode {
-        Some(glutin::VirtualKeyCode::A) => self.control.left = false,
-        Some(glutin::VirtualKeyCode::D) => self.control.right = false,
-        Some(glutin::VirtualKeyCode::W) => self.control.up = false,
-        Some(glutin::VirtualKeyCode::S) => self.control.down = false,
-        Some(glutin::VirtualKeyCode::Q) => self.control.change = false,
-        Some(glutin::VirtualKeyCode::E) => self.control.throw = false,
-        Some(glutin::VirtualKeyCode::X) => self.control.prone = false,
-        _ => {}
-      },
-    }
-  }
-
-  pub fn update_mouse_button(&mut self, input: &(glutin::ElementState, glutin::MouseButton)) {
-    let pressed = match input.0 {
-      glutin::ElementState::Pressed => true,
-      glutin::ElementState::Released => false,
-    };
-    match input.1 {
-      glutin::MouseButton::Left => self.control.fire = pressed,
-      glutin::MouseButton::Right => self.control.jets = pressed,
-      _ => (),
-    }
-  }
-
-  pub fn new(state: &mut MainState) -> Soldier {
-    let control: Control = Default::default();
+  fn new_skeleton() -> ParticleSystem {
     let mut gostek = ParticleSystem::new();
     gostek.load_from_file(&String::from("gostek.po"), 4.50);
     gostek.timestep = 1.00;
     gostek.gravity = 1.06 * GRAV;
     gostek.v_damping = 0.9945;
+    gostek
+  }
+
+  /// Weapons a soldier spawns with: primary, secondary and grenades.
+  fn loadout(
+    state: &MainState,
+    primary: WeaponKind,
+    secondary: WeaponKind,
+  ) -> (Weapon, Weapon, Weapon) {
+    let primary = Weapon::new(primary, &state.weapons);
+    let secondary = Weapon::new(secondary, &state.weapons);
+    let mut tertiary = Weapon::new(WeaponKind::FragGrenade, &state.weapons);
+    tertiary.ammo_count = state.settings.grenades;
+    (primary, secondary, tertiary)
+  }
+
+  pub fn new(state: &mut MainState, num: usize, team: u8, position: Vector2<f32>) -> Soldier {
+    let control: Control = Default::default();
+    let gostek = Soldier::new_skeleton();
+    let loadout_primary = state.settings.primary_weapon;
+    let loadout_secondary = state.settings.secondary_weapon;
+    let (primary, secondary, tertiary) =
+      Soldier::loadout(state, loadout_primary, loadout_secondary);
     state.soldier_parts.create_part(
-      Vector2::new(
-        state.map.spawnpoints[0].x as f32,
-        state.map.spawnpoints[0].y as f32,
-      ),
+      position,
       Vector2::new(0.0f32, 0.0f32),
       1.00,
-      1,
+      num as i32,
     );
     Soldier {
       active: true,
       dead_meat: false,
       style: 0,
-      num: 1,
-      visible: 1,
+      num,
+      team,
+      appearance: PlayerAppearance::default().with_team(team),
+      visible: VISIBLE_TIME,
       on_ground: false,
       on_ground_for_law: false,
       on_ground_last_frame: false,
       on_ground_permanent: false,
       direction: 1,
       old_direction: 1,
-      health: 150.0,
+      health: max_health(&state.settings),
       alpha: 255,
       jets_count: 0,
       jets_count_prev: 0,
-      wear_helmet: 0,
-      has_cigar: 1,
+      wear_helmet: 1,
+      has_cigar: CIGAR_POCKET,
       vest: 0.0,
       idle_time: 0,
       idle_random: 0,
       position: 0,
       on_fire: 0,
+      igniter: None,
       collider_distance: 255,
       half_dead: false,
+      half_dead_time: 0,
+      respawn_counter: 0,
+      last_hit: None,
+      primary_weapon: primary,
+      secondary_weapon: secondary,
+      loadout_primary,
+      loadout_secondary,
+      tertiary_weapon: tertiary,
+      fired: false,
+      bink: 0,
+      recoil: 0.0,
+      grenade_charge: 0,
+      landing_time: 0,
+      bonus: None,
+      bonus_time: 0,
       skeleton: gostek,
       legs_animation: state.anims.stand.clone(),
       body_animation: state.anims.stand.clone(),
-      control: control,
+      control,
     }
   }
   pub fn legs_apply_animation(&mut self, anim: Box<Animation>, curr: i32) {
@@ -172,19 +257,500 @@ impl Soldier {
     if polytype == PolyType::Deadly || polytype == PolyType::BloodyDeadly
       || polytype == PolyType::Explosive
     {
-      state.soldier_parts.pos[self.num] = Vector2::new(
-        state.map.spawnpoints[0].x as f32,
-        state.map.spawnpoints[0].y as f32,
-      );
+      // No vest saves from these, the kill goes to whoever hit the soldier last if anyone did
+      self.die(state, DeathType::Brutal);
+    } else if polytype == PolyType::Hurts {
+      let damage = Damage::new(DamageSource::Polygon, HURTS_DAMAGE);
+      self.apply_damage(state, &damage);
+    } else if polytype == PolyType::Lava {
+      self.ignite(None);
+    }
+  }
+
+  /// Applies a hit to the soldier. The vest absorbs part of the damage, a lethal hit leaves the
+  /// soldier half dead for a moment (or kills it right away if it was a brutal one) and any
+  /// hit on a dead soldier just pushes the ragdoll around.
+  pub fn apply_damage(&mut self, state: &mut MainState, damage: &Damage) {
+    if self.dead_meat {
+      for i in 1..(self.skeleton.part_count + 1) as usize {
+        self.skeleton.old_pos[i] -= damage.impulse;
+      }
+      return;
+    }
+
+    let mut amount = damage.amount;
+
+    if self.vest > 0.0 {
+      let absorbed = f32::min(self.vest, amount * VEST_ABSORPTION);
+      self.vest -= absorbed;
+      amount -= absorbed;
+    }
+
+    self.health -= amount;
+    self.last_hit = Some(*damage);
+    state.soldier_parts.velocity[self.num] += damage.impulse;
+
+    if self.health <= 0.0 {
+      let death = if self.health < BRUTAL_DEATH_HEALTH {
+        DeathType::Brutal
+      } else if self.health < HEADCHOP_DEATH_HEALTH && damage.part == BodyPart::Head {
+        DeathType::Headchop
+      } else {
+        DeathType::Normal
+      };
+
+      if self.half_dead || death != DeathType::Normal {
+        self.die(state, death);
+      } else {
+        self.half_dead = true;
+        self.half_dead_time = HALF_DEAD_TIME;
+        self.control.free_controls();
+
+        // Goes down and crawls around until bleeding out
+        self.legs_apply_animation(state.anims.prone.clone(), 1);
+        self.body_apply_animation(state.anims.prone.clone(), 1);
+      }
+    }
+  }
+
+  pub fn die(&mut self, state: &mut MainState, death: DeathType) {
+    if self.dead_meat {
+      return;
+    }
+
+    self.dead_meat = true;
+    self.half_dead = false;
+    self.health = f32::min(self.health, 0.0);
+    self.respawn_counter = state.settings.respawn_time;
+    self.control.free_controls();
+
+    // Going limp, set directly as half-dead soldiers are stuck prone otherwise
+    self.legs_animation = state.anims.fall.clone();
+    self.body_animation = state.anims.fall.clone();
+
+    // The ragdoll keeps the momentum of the soldier and the blow that killed it
+    let mut velocity = state.soldier_parts.velocity[self.num];
+
+    if let Some(hit) = self.last_hit {
+      velocity += hit.impulse;
+    }
+
+    for i in 1..(self.skeleton.part_count + 1) as usize {
+      self.skeleton.old_pos[i] = self.skeleton.pos[i] - velocity;
+    }
+
+    self.end_bonus(state);
+
+    if self.primary_weapon.kind != WeaponKind::Hands {
+      self.drop_weapon(state, velocity);
+    }
+
+    let detached: &[usize] = match death {
+      DeathType::Normal => &[],
+      DeathType::Headchop => &[HEAD],
+      DeathType::Brutal => &[HEAD, LEFT_ELBOW, RIGHT_ELBOW],
+    };
+
+    for i in 1..(self.skeleton.constraint_count + 1) as usize {
+      let constraint = &mut self.skeleton.constraints[i];
+      let (a, b) = (constraint.part_a as usize, constraint.part_b as usize);
+
+      if detached.contains(&a) || detached.contains(&b) {
+        constraint.active = false;
+      }
+    }
+
+    let (killer, source) = match self.last_hit {
+      Some(hit) => (hit.attacker, hit.source),
+      None => (None, DamageSource::Polygon),
+    };
+
+    state.events.push(GameEvent::Kill {
+      killer,
+      victim: self.num,
+      source,
+    });
+  }
+
+  pub fn respawn(&mut self, state: &mut MainState, position: Vector2<f32>) {
+    state.soldier_parts.create_part(position, Vector2::zeros(), 1.00, self.num as i32);
+
+    self.skeleton = Soldier::new_skeleton();
+    self.dead_meat = false;
+    self.half_dead = false;
+    self.health = max_health(&state.settings);
+    self.vest = 0.0;
+    self.bink = 0;
+    self.recoil = 0.0;
+    self.visible = VISIBLE_TIME;
+    self.grenade_charge = 0;
+    self.landing_time = 0;
+    self.bonus = None;
+    self.bonus_time = 0;
+    self.extinguish();
+    self.wear_helmet = iif!(self.appearance.headgear == Headgear::None, 0, 1);
+    self.jets_count = state.map.start_jet;
+    self.last_hit = None;
+    self.control.free_controls();
+    let (primary, secondary, tertiary) =
+      Soldier::loadout(state, self.loadout_primary, self.loadout_secondary);
+    self.primary_weapon = primary;
+    self.secondary_weapon = secondary;
+    self.tertiary_weapon = tertiary;
+    self.legs_animation = state.anims.stand.clone();
+    self.body_animation = state.anims.stand.clone();
+
+    state.events.push(GameEvent::Respawn { soldier: self.num });
+  }
+
+  /// Dresses the soldier up, with the shirt in its team's color in team modes.
+  pub fn set_appearance(&mut self, appearance: PlayerAppearance) {
+    self.appearance = appearance.with_team(self.team);
+    self.wear_helmet = iif!(appearance.headgear == Headgear::None, 0, 1);
+  }
+
+  /// Changes the weapons the soldier spawns with, and hands them over right away.
+  pub fn choose_weapons(&mut self, state: &MainState, primary: WeaponKind, secondary: WeaponKind) {
+    self.loadout_primary = primary;
+    self.loadout_secondary = secondary;
+    self.primary_weapon = Weapon::new(primary, &state.weapons);
+    self.secondary_weapon = Weapon::new(secondary, &state.weapons);
+  }
+
+  /// Starts reloading the weapon in hand. Magazine fed weapons go through the clip out and clip
+  /// in animations, bows have their own and everything else uses the generic one.
+  pub fn reload_weapon(&mut self, state: &MainState) {
+    let kind = self.primary_weapon.kind;
+
+    self.primary_weapon.reload_time_count = state.weapons.get(kind).reload_time;
+    self.primary_weapon.start_up_time_count = 0;
+
+    let anim = if kind.is_bow() {
+      &state.anims.reload_bow
+    } else if kind.clip_reload() {
+      &state.anims.clip_out
+    } else {
+      &state.anims.reload
+    };
+
+    self.body_apply_animation(anim.clone(), 1);
+  }
+
+  /// Fires the weapon in hand, taking care of ammo, the fire interval and the recoil animation.
+  /// The projectiles are queued in `MainState::emitter`.
+  pub fn fire_weapon(&mut self, state: &mut MainState) {
+    let kind = self.primary_weapon.kind;
+    let stats = *state.weapons.get(kind);
+
+    self.emit_bullets(state, &stats);
+
+    // Melee weapons never run out
+    if kind != WeaponKind::Hands && kind != WeaponKind::Knife {
+      self.primary_weapon.ammo_count -= 1;
+    }
+
+    self.primary_weapon.fire_interval_count = stats.fire_interval;
+    self.fired = true;
+
+    if state.settings.realistic_mode {
+      self.recoil = f32::min(self.recoil + stats.recoil as f32 * RECOIL_ANGLE, MAX_RECOIL);
+    }
+
+    let recoil = match kind {
+      WeaponKind::Hands | WeaponKind::Knife => Some(&state.anims.punch),
+      WeaponKind::Spas12 => Some(&state.anims.shotgun),
+      WeaponKind::Barrett => Some(&state.anims.barret),
+      WeaponKind::Ruger77 | WeaponKind::M79 | WeaponKind::Law => Some(&state.anims.recoil),
+      WeaponKind::Chainsaw | WeaponKind::Flamer | WeaponKind::Minigun => None,
+      WeaponKind::Bow | WeaponKind::FlameBow => None,
+      WeaponKind::FragGrenade | WeaponKind::ClusterGrenade => None,
+      _ => Some(&state.anims.small_recoil),
+    };
+
+    let recoil = match recoil {
+      Some(anim) if anim.id == state.anims.punch.id => Some(anim),
+      Some(_) if self.position == POS_PRONE => None,
+      Some(_) if self.position == POS_CROUCH => {
+        if self.collider_distance < 255 {
+          Some(&state.anims.hands_up_recoil)
+        } else {
+          Some(&state.anims.aim_recoil)
+        }
+      }
+      recoil => recoil,
+    };
+
+    if let Some(anim) = recoil {
+      self.body_apply_animation(anim.clone(), 1);
+      self.body_animation.curr_frame = 1;
+    }
+  }
+
+  /// Applies a kit's effect. Returns false if the soldier doesn't need it, in which case the kit
+  /// stays where it is.
+  pub fn pick_up_kit(&mut self, state: &mut MainState, kind: KitKind) -> bool {
+    match kind {
+      KitKind::Medikit => {
+        let health = max_health(&state.settings);
+
+        if self.health >= health && self.on_fire == 0 {
+          return false;
+        }
+
+        self.health = health;
+        self.extinguish();
+      }
+      KitKind::Grenadekit => {
+        let tertiary = &mut self.tertiary_weapon;
+
+        if tertiary.kind == WeaponKind::FragGrenade && tertiary.ammo_count >= MAX_GRENADES {
+          return false;
+        }
+
+        *tertiary = Weapon::new(WeaponKind::FragGrenade, &state.weapons);
+        tertiary.ammo_count = MAX_GRENADES;
+      }
+      KitKind::Clusterkit => {
+        let tertiary = &mut self.tertiary_weapon;
+
+        if tertiary.kind == WeaponKind::ClusterGrenade && tertiary.ammo_count >= CLUSTER_KIT_GRENADES {
+          return false;
+        }
+
+        *tertiary = Weapon::new(WeaponKind::ClusterGrenade, &state.weapons);
+        tertiary.ammo_count = CLUSTER_KIT_GRENADES;
+      }
+      KitKind::Vestkit => {
+        if self.vest >= MAX_VEST {
+          return false;
+        }
+
+        self.vest = MAX_VEST;
+      }
+      KitKind::Berserkerkit | KitKind::Predatorkit | KitKind::Flamerkit => {
+        // Only one bonus at a time
+        if self.bonus.is_some() {
+          return false;
+        }
+
+        let (bonus, time) = match kind {
+          KitKind::Berserkerkit => (Bonus::Berserker, kit::BERSERKER_TIME),
+          KitKind::Predatorkit => (Bonus::Predator, kit::PREDATOR_TIME),
+          _ => {
+            let flamer = Weapon::new(WeaponKind::Flamer, &state.weapons);
+            let previous = ::std::mem::replace(&mut self.primary_weapon, flamer);
+            (Bonus::Flamer(previous), kit::FLAMER_TIME)
+          }
+        };
+
+        self.bonus = Some(bonus);
+        self.bonus_time = time;
+      }
+    }
+
+    true
+  }
+
+  /// Ends the current bonus, giving back the weapon the flamer replaced.
+  pub fn end_bonus(&mut self, state: &MainState) {
+    if let Some(Bonus::Flamer(previous)) = self.bonus {
+      self.primary_weapon = previous;
+      self.body_apply_animation(state.anims.change.clone(), 3);
+    }
+
+    self.bonus = None;
+    self.bonus_time = 0;
+  }
+
+  pub fn has_flamer(&self) -> bool {
+    match self.bonus {
+      Some(Bonus::Flamer(_)) => true,
+      _ => false,
+    }
+  }
+
+  /// Multiplier for the damage dealt by the soldier's bullets.
+  pub fn damage_multiplier(&self) -> f32 {
+    match self.bonus {
+      Some(Bonus::Berserker) => kit::BERSERKER_DAMAGE,
+      _ => 1.0,
+    }
+  }
+
+  /// Throws the weapon in hand towards the aim, leaving the soldier unarmed.
+  pub fn throw_weapon(&mut self, state: &mut MainState) {
+    if self.primary_weapon.kind == WeaponKind::Hands || self.has_flamer() {
+      return;
+    }
+
+    let position = self.skeleton.pos[RIGHT_HAND];
+    let aim = Vector2::new(self.control.mouse_aim_x as f32, self.control.mouse_aim_y as f32);
+    let direction = calc::vec2normalize(aim - position, aim - position);
+    let velocity = direction * WEAPON_THROW_SPEED + state.soldier_parts.velocity[self.num];
+
+    self.drop_weapon(state, velocity);
+  }
+
+  /// Takes a weapon lying around. That needs free hands, so walking past a weapon doesn't swap
+  /// guns back and forth, except for the rambo bow which replaces whatever is in hand.
+  pub fn pick_up_weapon(&mut self, state: &mut MainState, weapon: Weapon) -> bool {
+    if self.primary_weapon.kind != WeaponKind::Hands {
+      if weapon.kind != WeaponKind::Bow {
+        return false;
+      }
+
+      let velocity = state.soldier_parts.velocity[self.num];
+      self.drop_weapon(state, velocity);
+    }
+
+    self.primary_weapon = weapon;
+    true
+  }
+
+  /// Turns the weapon in hand into a thing in the world, keeping its ammo.
+  fn drop_weapon(&mut self, state: &mut MainState, velocity: Vector2<f32>) {
+    let mut weapon = self.primary_weapon;
+    weapon.fire_interval_count = 0;
+    weapon.reload_time_count = 0;
+    weapon.start_up_time_count = 0;
+
+    state.emitter.push(EmitterItem::Thing(ThingParams {
+      kind: ThingKind::Weapon(weapon),
+      owner: Some(self.num),
+      position: self.skeleton.pos[RIGHT_HAND],
+      velocity,
+    }));
+
+    self.primary_weapon = Weapon::new(WeaponKind::Hands, &state.weapons);
+  }
+
+  /// Throws the grenade being charged, harder the longer it was held.
+  pub fn throw_grenade(&mut self, state: &mut MainState) {
+    let charge = self.grenade_charge as f32 / GRENADE_MAX_CHARGE as f32;
+    self.grenade_charge = 0;
+
+    if self.tertiary_weapon.ammo_count <= 0 {
+      return;
+    }
+
+    self.tertiary_weapon.ammo_count -= 1;
+
+    let stats = *state.weapons.get(self.tertiary_weapon.kind);
+    let position = self.skeleton.pos[RIGHT_HAND];
+    let aim = Vector2::new(self.control.mouse_aim_x as f32, self.control.mouse_aim_y as f32);
+    let direction = calc::vec2normalize(aim - position, aim - position);
+    let power = GRENADE_MIN_POWER + (GRENADE_MAX_POWER - GRENADE_MIN_POWER) * charge;
+    let velocity = direction * stats.speed * power
+      + state.soldier_parts.velocity[self.num] * stats.inherited_velocity;
+
+    state.emitter.push(EmitterItem::Grenade(BulletParams {
+      style: stats.bullet_style,
+      weapon: self.tertiary_weapon.kind,
+      owner: self.num,
+      team: self.team,
+      position,
+      velocity,
+    }));
+  }
+
+  fn emit_bullets(&mut self, state: &mut MainState, stats: &WeaponStats) {
+    let position = self.skeleton.pos[RIGHT_HAND];
+    let aim = Vector2::new(self.control.mouse_aim_x as f32, self.control.mouse_aim_y as f32);
+    let direction = calc::vec2normalize(aim - position, aim - position);
+    let velocity = state.soldier_parts.velocity[self.num];
+
+    // Spread is a random offset added to the aim direction, which grows when moving and binked
+    let spread = stats.bullet_spread / 2.0 + stats.movement_acc * calc::vec2length(velocity)
+      + self.bink as f32 * BINK_SPREAD;
+
+    let pellets = iif!(stats.bullet_style == BulletStyle::Shotgun, SHOTGUN_PELLETS, 1);
+
+    for _ in 0..pellets {
+      let mut dir = direction;
+
+      if spread > 0.0 {
+        dir.x += state.rng.gen_range(-spread, spread);
+        dir.y += state.rng.gen_range(-spread, spread);
+        dir = calc::vec2normalize(dir, dir);
+      }
+
+      state.emitter.push(EmitterItem::Bullet(BulletParams {
+        style: stats.bullet_style,
+        weapon: self.primary_weapon.kind,
+        owner: self.num,
+        team: self.team,
+        position,
+        velocity: dir * stats.speed + velocity * stats.inherited_velocity,
+      }));
+    }
+
+    // Negative bink disturbs the shooter's own aim
+    if stats.bink < 0 {
+      self.bink = i32::min(self.bink - stats.bink, MAX_BINK);
+    }
+  }
+
+  /// Advances the timers of the weapon in hand. Reloads complete once their timer runs out.
+  fn update_weapons(&mut self, state: &MainState) {
+    if self.bink > 0 {
+      self.bink -= 1;
+    }
+
+    self.recoil *= RECOIL_DECAY;
+
+    if self.bonus.is_some() {
+      self.bonus_time -= 1;
+
+      if self.bonus_time <= 0 {
+        self.end_bonus(state);
+      }
+    }
+
+    let stats = state.weapons.get(self.primary_weapon.kind);
+    let weapon = &mut self.primary_weapon;
+
+    if weapon.fire_interval_count > 0 {
+      weapon.fire_interval_count -= 1;
+    }
+
+    // Weapons like the minigun need to spin up before they start firing
+    if stats.start_up_time > 0 {
+      if self.control.fire && weapon.ammo_count > 0 && !weapon.is_reloading() {
+        weapon.start_up_time_count = i32::min(weapon.start_up_time_count + 1, stats.start_up_time);
+      } else {
+        weapon.start_up_time_count = 0;
+      }
+    }
+
+    if weapon.reload_time_count > 0 {
+      weapon.reload_time_count -= 1;
+
+      if weapon.reload_time_count == 0 {
+        weapon.ammo_count = stats.ammo;
+      }
     }
   }
 
-  pub fn update(&mut self, state: &mut MainState) {
+  pub fn update(&mut self, state: &mut MainState, input: &InputFrame) {
     let mut body_y = 0.0;
 
     let mut arm_s;
 
-    self.control(state);
+    self.control(state, input);
+
+    if !self.dead_meat {
+      self.update_weapons(state);
+    }
+
+    self.update_burning(state);
+
+    if self.half_dead {
+      self.half_dead_time -= 1;
+
+      if self.half_dead_time <= 0 {
+        self.die(state, DeathType::Normal);
+      }
+    }
 
     self.skeleton.old_pos[21] = self.skeleton.pos[21];
     self.skeleton.old_pos[23] = self.skeleton.pos[23];
@@ -243,7 +809,7 @@ impl Soldier {
             || (i == 18)
           {
             // legs
-            self.skeleton.pos[i].x = state.soldier_parts.pos[self.num].x + self.direction as f32 *
+            self.skeleton.pos[i].x = state.soldier_parts.pos[self.num].x + f32::from(self.direction) *
               self.legs_animation.frame[self.legs_animation.curr_frame as usize].pos[i].x;
             self.skeleton.pos[i].y = state.soldier_parts.pos[self.num].y +
               self.legs_animation.frame[self.legs_animation.curr_frame as usize].pos[i].y;
@@ -383,12 +949,11 @@ impl Soldier {
     for i in 1..20 {
       if self.dead_meat || self.half_dead {
         if (i != 17) && (i != 18) && (i != 19) && (i != 20) && (i != 8) && (i != 7) && (i != 21) {
-          let mut position = Vector2::new(
+          let position = Vector2::new(
             state.soldier_parts.pos[self.num].x,
             state.soldier_parts.pos[self.num].y,
           );
           self.on_ground = self.check_skeleton_map_collision(state, i, position.x, position.y);
-          println!("ok");
         }
       }
     }
@@ -399,6 +964,9 @@ impl Soldier {
 
       self.on_ground = false;
 
+      // Collisions slow the soldier down, so remember how fast it was falling
+      let fall_speed = state.soldier_parts.velocity[self.num].y;
+
       let position = Vector2::new(
         state.soldier_parts.pos[self.num].x,
         state.soldier_parts.pos[self.num].y,
@@ -472,6 +1040,10 @@ impl Soldier {
         self.on_ground_permanent = self.on_ground;
       }
 
+      if self.on_ground && !self.on_ground_last_frame {
+        self.land(state, fall_speed);
+      }
+
       self.on_ground_last_frame = self.on_ground;
 
       if (self.jets_count < state.map.start_jet) && !(self.control.jets) {
@@ -482,7 +1054,10 @@ impl Soldier {
         }
       }
 
-      self.alpha = 255;
+      self.alpha = match self.bonus {
+        Some(Bonus::Predator) => kit::PREDATOR_ALPHA,
+        _ => 255,
+      };
 
       self.skeleton.do_verlet_timestep_for(22, 29);
       self.skeleton.do_verlet_timestep_for(24, 30);
@@ -509,6 +1084,90 @@ impl Soldier {
       state.soldier_parts.velocity[self.num].y = MAX_VELOCITY;
     }
   }
+
+  /// Sets the soldier on fire, or keeps it burning for longer. Fires started by the map keep
+  /// crediting whoever lit the soldier up before.
+  pub fn ignite(&mut self, igniter: Option<usize>) {
+    if self.dead_meat {
+      return;
+    }
+
+    self.on_fire = BURN_TIME;
+    self.igniter = igniter.or(self.igniter);
+  }
+
+  pub fn extinguish(&mut self) {
+    self.on_fire = 0;
+    self.igniter = None;
+  }
+
+  /// Burning hurts every now and then and throws off flames from all over the skeleton. Rolling
+  /// puts the fire out, and corpses burn out without taking any more damage.
+  fn update_burning(&mut self, state: &mut MainState) {
+    if self.on_fire == 0 {
+      return;
+    }
+
+    if self.body_animation.id == state.anims.roll.id
+      || self.body_animation.id == state.anims.roll_back.id
+    {
+      self.extinguish();
+      return;
+    }
+
+    self.on_fire -= 1;
+
+    if self.on_fire % FIRE_SPARK_INTERVAL == 0 {
+      let point = state.rng.gen_range(1, 21);
+      let kind = iif!(state.rng.gen_range(0, 4) == 0, SparkKind::Smoke, SparkKind::Fire);
+      let dx: f32 = state.rng.gen_range(-0.3, 0.3);
+      let dy: f32 = state.rng.gen_range(-0.6, -0.2);
+
+      state.emitter.push(EmitterItem::Spark(SparkParams {
+        kind,
+        position: self.skeleton.pos[point],
+        velocity: state.soldier_parts.velocity[self.num] + Vector2::new(dx, dy),
+      }));
+    }
+
+    if !self.dead_meat && self.on_fire % BURN_INTERVAL == 0 {
+      let damage = Damage::new(DamageSource::Fire, BURN_DAMAGE);
+      let damage = match self.igniter {
+        Some(igniter) => damage.attacker(igniter),
+        None => damage,
+      };
+
+      self.apply_damage(state, &damage);
+    }
+
+    if self.on_fire == 0 {
+      self.igniter = None;
+    }
+  }
+
+  /// Called when the soldier hits the ground falling at the given speed. Hard landings leave it
+  /// crouching for a moment with a shaken aim, and really hard ones hurt, see `FallDamage`.
+  fn land(&mut self, state: &mut MainState, speed: f32) {
+    let fall = state.settings.fall_damage;
+
+    if self.half_dead || speed <= fall.hard_landing_speed {
+      return;
+    }
+
+    self.landing_time = HARD_LANDING_TIME;
+    self.bink = i32::min(self.bink + HARD_LANDING_BINK, MAX_BINK);
+
+    state.events.push(GameEvent::HardLanding {
+      soldier: self.num,
+      speed,
+    });
+
+    if speed > fall.damage_speed && fall.damage > 0.0 {
+      let damage = Damage::new(DamageSource::Fall, (speed - fall.damage_speed) * fall.damage);
+      self.apply_damage(state, &damage);
+    }
+  }
+
   pub fn check_map_collision(&mut self, state: &mut MainState, x: f32, y: f32, area: i32) -> bool {
     let s_pos = Vector2::new(x, y);
 
@@ -781,7 +1440,7 @@ impl Soldier {
         }
       }
     }
-    return false;
+    false
   }
   pub fn check_skeleton_map_collision(
     &mut self,
@@ -801,12 +1460,12 @@ impl Soldier {
       for j in 0..state.map.sectors_poly[rx as usize][ry as usize].polys.len() {
         let w = state.map.sectors_poly[rx as usize][ry as usize].polys[j] - 1;
 
-        if state.map.point_in_poly_edges(pos.x, pos.y, w as i32) {
+        if state.map.point_in_poly_edges(pos.x, pos.y, i32::from(w)) {
           let mut d = 0.0;
           let mut b = 0;
           let mut perp = state
             .map
-            .closest_perpendicular(w as i32, pos, &mut d, &mut b);
+            .closest_perpendicular(i32::from(w), pos, &mut d, &mut b);
           perp = calc::vec2normalize(perp, perp);
           perp *= d;
 
@@ -828,14 +1487,14 @@ impl Soldier {
         for j in 0..state.map.sectors_poly[rx as usize][ry as usize].polys.len() {
           let w = state.map.sectors_poly[rx as usize][ry as usize].polys[j] - 1;
           //if (Map.PolyType[w] <> POLY_TYPE_DOESNT) and (Map.PolyType[w] <> POLY_TYPE_ONLY_BULLETS) then
-          if state.map.point_in_poly_edges(pos.x, pos.y, w as i32) {
+          if state.map.point_in_poly_edges(pos.x, pos.y, i32::from(w)) {
             let mut d = 0.0;
             let mut b = 0;
             let mut perp = state
               .map
-              .closest_perpendicular(w as i32, pos, &mut d, &mut b);
+              .closest_perpendicular(i32::from(w), pos, &mut d, &mut b);
             perp = calc::vec2normalize(perp, perp);
-            perp = perp * d;
+            perp *= d;
 
             self.skeleton.pos[i as usize] = self.skeleton.old_pos[i as usize];
             self.skeleton.pos[i as usize] -= perp;
@@ -844,6 +1503,6 @@ impl Soldier {
         }
       }
     }
-    return result;
+    result
   }
 }
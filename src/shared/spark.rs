@@ -0,0 +1,63 @@
+use na::Vector2;
+
+/// Sparks drift upwards like hot air, while slowing down.
+const SPARK_RISE: f32 = 0.02;
+const SPARK_DAMPING: f32 = 0.95;
+
+const FIRE_LIFE: i32 = 32;
+const SMOKE_LIFE: i32 = 60;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SparkKind {
+  Fire,
+  Smoke,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct SparkParams {
+  pub kind: SparkKind,
+  pub position: Vector2<f32>,
+  pub velocity: Vector2<f32>,
+}
+
+/// A short-lived particle that's only there to be looked at, like the flames coming off a
+/// burning soldier. Sparks don't collide with anything.
+#[derive(Debug, Copy, Clone)]
+pub struct Spark {
+  pub active: bool,
+  pub kind: SparkKind,
+  pub pos: Vector2<f32>,
+  pub velocity: Vector2<f32>,
+  /// Ticks the spark has been around for.
+  pub age: i32,
+  pub life: i32,
+}
+
+impl Spark {
+  pub fn new(params: &SparkParams) -> Spark {
+    Spark {
+      active: true,
+      kind: params.kind,
+      pos: params.position,
+      velocity: params.velocity,
+      age: 0,
+      life: match params.kind {
+        SparkKind::Fire => FIRE_LIFE,
+        SparkKind::Smoke => SMOKE_LIFE,
+      },
+    }
+  }
+
+  /// How far along its life the spark is, from 0 to 1.
+  pub fn progress(&self) -> f32 {
+    self.age as f32 / self.life as f32
+  }
+
+  pub fn update(&mut self) {
+    self.age += 1;
+    self.active = self.age < self.life;
+    self.velocity.y -= SPARK_RISE;
+    self.velocity *= SPARK_DAMPING;
+    self.pos += self.velocity;
+  }
+}
@@ -0,0 +1,180 @@
+use na::Vector2;
+use rand::Rng;
+
+use shared::calc;
+use shared::mapfile::MapSpawnpoint;
+
+pub const TEAM_NONE: u8 = 0;
+pub const TEAM_ALPHA: u8 = 1;
+pub const TEAM_BRAVO: u8 = 2;
+pub const TEAM_CHARLIE: u8 = 3;
+pub const TEAM_DELTA: u8 = 4;
+
+/// Spawning closer than this to an enemy is avoided whenever possible.
+const SAFE_SPAWN_DISTANCE: f32 = 250.0;
+
+fn spawnpoint_position(spawn: &MapSpawnpoint) -> Vector2<f32> {
+  Vector2::new(spawn.x as f32, spawn.y as f32)
+}
+
+fn candidates(spawnpoints: &[MapSpawnpoint], team: u8) -> Vec<&MapSpawnpoint> {
+  let team = i32::from(team);
+
+  // Team spawns first, then general spawns, then any player spawn at all
+  let mut points: Vec<&MapSpawnpoint> = spawnpoints
+    .iter()
+    .filter(|s| s.active && s.team == team)
+    .collect();
+
+  if points.is_empty() && team != i32::from(TEAM_NONE) {
+    points = spawnpoints
+      .iter()
+      .filter(|s| s.active && s.team == i32::from(TEAM_NONE))
+      .collect();
+  }
+
+  if points.is_empty() {
+    points = spawnpoints
+      .iter()
+      .filter(|s| s.active && s.team >= i32::from(TEAM_NONE) && s.team <= i32::from(TEAM_DELTA))
+      .collect();
+  }
+
+  points
+}
+
+/// Picks a spawn position for a soldier of the given team. Inactive spawnpoints and those of
+/// other teams are skipped, and points away from `enemies` are preferred. All randomness comes
+/// from `rng`, so the result is reproducible for a given seed.
+pub fn select_spawnpoint<R: Rng>(
+  spawnpoints: &[MapSpawnpoint],
+  team: u8,
+  enemies: &[Vector2<f32>],
+  rng: &mut R,
+) -> Vector2<f32> {
+  let points = candidates(spawnpoints, team);
+
+  if points.is_empty() {
+    return match spawnpoints.first() {
+      Some(spawn) => spawnpoint_position(spawn),
+      None => Vector2::zeros(),
+    };
+  }
+
+  let enemy_distance = |spawn: &MapSpawnpoint| {
+    enemies
+      .iter()
+      .map(|&enemy| calc::distance(enemy, spawnpoint_position(spawn)))
+      .fold(::std::f32::MAX, f32::min)
+  };
+
+  let safe: Vec<&MapSpawnpoint> = points
+    .iter()
+    .filter(|s| enemy_distance(s) >= SAFE_SPAWN_DISTANCE)
+    .cloned()
+    .collect();
+
+  if !safe.is_empty() {
+    return spawnpoint_position(safe[rng.gen_range(0, safe.len())]);
+  }
+
+  // Every point is near an enemy, so take the one furthest away from them
+  let mut best = points[0];
+
+  for &spawn in &points[1..] {
+    if enemy_distance(spawn) > enemy_distance(best) {
+      best = spawn;
+    }
+  }
+
+  spawnpoint_position(best)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rand::{SeedableRng, XorShiftRng};
+
+  fn spawnpoint(x: i32, team: u8, active: bool) -> MapSpawnpoint {
+    MapSpawnpoint {
+      active,
+      x,
+      y: 0,
+      team: i32::from(team),
+    }
+  }
+
+  fn rng(seed: u32) -> XorShiftRng {
+    XorShiftRng::from_seed([seed, 1, 2, 3])
+  }
+
+  // Spawnpoints sit on whole coordinates, so the picked one can be compared exactly
+  fn pick(spawnpoints: &[MapSpawnpoint], team: u8, enemies: &[Vector2<f32>], seed: u32) -> i32 {
+    select_spawnpoint(spawnpoints, team, enemies, &mut rng(seed)).x as i32
+  }
+
+  #[test]
+  fn same_seed_picks_same_spawnpoints() {
+    let spawnpoints: Vec<MapSpawnpoint> = (0..16)
+      .map(|i| spawnpoint(i * 1000, TEAM_NONE, true))
+      .collect();
+    let (mut a, mut b) = (rng(7), rng(7));
+
+    for _ in 0..32 {
+      assert_eq!(
+        select_spawnpoint(&spawnpoints, TEAM_NONE, &[], &mut a),
+        select_spawnpoint(&spawnpoints, TEAM_NONE, &[], &mut b)
+      );
+    }
+  }
+
+  #[test]
+  fn only_active_spawnpoints_of_own_team() {
+    let spawnpoints = [
+      spawnpoint(0, TEAM_NONE, true),
+      spawnpoint(1000, TEAM_ALPHA, false),
+      spawnpoint(2000, TEAM_ALPHA, true),
+      spawnpoint(3000, TEAM_BRAVO, true),
+    ];
+
+    for seed in 0..32 {
+      assert_eq!(pick(&spawnpoints, TEAM_ALPHA, &[], seed), 2000);
+      assert_eq!(pick(&spawnpoints, TEAM_BRAVO, &[], seed), 3000);
+    }
+  }
+
+  #[test]
+  fn falls_back_to_general_spawnpoints() {
+    let spawnpoints = [
+      spawnpoint(0, TEAM_ALPHA, true),
+      spawnpoint(1000, TEAM_NONE, true),
+      spawnpoint(2000, TEAM_BRAVO, false),
+    ];
+
+    for seed in 0..32 {
+      assert_eq!(pick(&spawnpoints, TEAM_BRAVO, &[], seed), 1000);
+    }
+  }
+
+  #[test]
+  fn avoids_spawnpoints_near_enemies() {
+    let spawnpoints = [
+      spawnpoint(0, TEAM_NONE, true),
+      spawnpoint(1000, TEAM_NONE, true),
+      spawnpoint(2000, TEAM_NONE, true),
+    ];
+    let enemies = [Vector2::new(100.0, 0.0), Vector2::new(1900.0, 0.0)];
+
+    for seed in 0..32 {
+      assert_eq!(pick(&spawnpoints, TEAM_NONE, &enemies, seed), 1000);
+    }
+
+    // With enemies everywhere the one furthest from them is the least bad
+    let enemies = [
+      Vector2::new(150.0, 0.0),
+      Vector2::new(1100.0, 0.0),
+      Vector2::new(2000.0, 0.0),
+    ];
+    assert_eq!(pick(&spawnpoints, TEAM_NONE, &enemies, 0), 0);
+  }
+}
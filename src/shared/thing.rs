@@ -0,0 +1,170 @@
+use na::Vector2;
+
+use shared::calc;
+use shared::state::{MainState, GRAV};
+use shared::parts::ParticleSystem;
+use shared::mapfile::PolyType;
+use shared::weapons::Weapon;
+use shared::kit::KitKind;
+
+const SECOND: i32 = 60;
+
+const THING_DAMPING: f32 = 0.99;
+/// Fraction of the speed into a polygon that is kept when bouncing off it.
+const THING_BOUNCINESS: f32 = 0.2;
+/// Fraction of the speed along a polygon that is kept when touching it.
+const THING_FRICTION: f32 = 0.8;
+
+const WEAPON_LENGTH: f32 = 10.0;
+const KIT_LENGTH: f32 = 12.0;
+pub const FLAG_LENGTH: f32 = 25.0;
+const WEAPON_TIMEOUT: i32 = 20 * SECOND;
+/// Ticks before whoever dropped a thing can pick it up again.
+pub const PICKUP_DELAY: i32 = SECOND;
+
+/// What a thing lying around in the world is.
+#[derive(Debug, Copy, Clone)]
+pub enum ThingKind {
+  Weapon(Weapon),
+  Kit(KitKind),
+  /// A team's flag. Flags are kept apart from other things, see `shared::flag`.
+  Flag(u8),
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct ThingParams {
+  pub kind: ThingKind,
+  pub owner: Option<usize>,
+  pub position: Vector2<f32>,
+  pub velocity: Vector2<f32>,
+}
+
+/// A physical object in the world, simulated as a couple of particles joined by a stick.
+pub struct Thing {
+  pub active: bool,
+  pub kind: ThingKind,
+  /// Soldier who dropped it, who can't pick it up right away.
+  pub owner: Option<usize>,
+  pub skeleton: ParticleSystem,
+  /// Ticks left before the thing disappears, if it ever does.
+  pub time_out: Option<i32>,
+  pub pickup_delay: i32,
+}
+
+/// Whether things stop at polygons of the given type.
+pub fn collides_with(polytype: PolyType) -> bool {
+  match polytype {
+    PolyType::Normal
+    | PolyType::OnlyPlayersCollide
+    | PolyType::Ice
+    | PolyType::Deadly
+    | PolyType::BloodyDeadly
+    | PolyType::Hurts
+    | PolyType::Regenerates
+    | PolyType::Lava
+    | PolyType::Bouncy
+    | PolyType::Explosive
+    | PolyType::HurtsFlaggers
+    | PolyType::NonFlaggersCollide => true,
+    _ => false,
+  }
+}
+
+impl Thing {
+  pub fn new(params: &ThingParams) -> Thing {
+    let mut skeleton = ParticleSystem::new();
+    skeleton.timestep = 1.00;
+    skeleton.gravity = GRAV;
+    skeleton.v_damping = THING_DAMPING;
+
+    let (length, time_out) = match params.kind {
+      ThingKind::Weapon(_) => (WEAPON_LENGTH, Some(WEAPON_TIMEOUT)),
+      ThingKind::Kit(_) => (KIT_LENGTH, None),
+      ThingKind::Flag(_) => (FLAG_LENGTH, None),
+    };
+
+    let half = Vector2::new(length / 2.0, 0.0);
+
+    for &(i, offset) in &[(1, -half), (2, half)] {
+      skeleton.create_part(params.position + offset, Vector2::zeros(), 1.00, i);
+      skeleton.old_pos[i as usize] = skeleton.pos[i as usize] - params.velocity;
+    }
+
+    skeleton.make_constraint(1, 2, length);
+
+    Thing {
+      active: true,
+      kind: params.kind,
+      owner: params.owner,
+      skeleton,
+      time_out,
+      pickup_delay: iif!(params.owner.is_some(), PICKUP_DELAY, 0),
+    }
+  }
+
+  pub fn position(&self) -> Vector2<f32> {
+    (self.skeleton.pos[1] + self.skeleton.pos[2]) * 0.5
+  }
+
+  /// Whether the given soldier may pick the thing up now.
+  pub fn can_pick_up(&self, soldier: usize) -> bool {
+    self.pickup_delay <= 0 || self.owner != Some(soldier)
+  }
+
+  pub fn update(&mut self, state: &mut MainState) {
+    if let Some(ref mut time_out) = self.time_out {
+      *time_out -= 1;
+    }
+
+    if self.pickup_delay > 0 {
+      self.pickup_delay -= 1;
+    }
+
+    let expired = self.time_out.map_or(false, |time_out| time_out <= 0);
+
+    if expired || state.map.sector_polys(self.position()).is_none() {
+      self.active = false;
+      return;
+    }
+
+    self.skeleton.do_verlet_timestep();
+
+    for i in 1..3 {
+      self.check_map_collision(state, i);
+    }
+  }
+
+  /// Pushes a particle out of any polygon it ended up in, bouncing it off the closest edge.
+  fn check_map_collision(&mut self, state: &mut MainState, i: usize) {
+    let pos = self.skeleton.pos[i];
+
+    let polys = match state.map.sector_polys(pos) {
+      Some(polys) => polys.to_vec(),
+      None => return,
+    };
+
+    for poly in polys {
+      let w = poly as usize - 1;
+
+      if !collides_with(state.map.polygons[w].polytype)
+        || !state.map.point_in_poly_edges(pos.x, pos.y, w as i32)
+      {
+        continue;
+      }
+
+      let mut d = 0.0;
+      let mut k = 0;
+      let perp = state.map.closest_perpendicular(w as i32, pos, &mut d, &mut k);
+      let normal = calc::vec2normalize(perp, perp);
+
+      let velocity = self.skeleton.pos[i] - self.skeleton.old_pos[i];
+      let along_normal = normal * velocity.dot(&normal);
+      let along_edge = velocity - along_normal;
+      let velocity = along_edge * THING_FRICTION - along_normal * THING_BOUNCINESS;
+
+      self.skeleton.pos[i] -= normal * d;
+      self.skeleton.old_pos[i] = self.skeleton.pos[i] - velocity;
+      return;
+    }
+  }
+}
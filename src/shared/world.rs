@@ -0,0 +1,690 @@
+use std::collections::HashMap;
+use na::Vector2;
+
+use shared::state::MainState;
+use shared::soldier::{Soldier, VISIBLE_TIME};
+use shared::bot::{Bot, BotProfile};
+use shared::input::InputFrame;
+use shared::spawn::{self, TEAM_NONE};
+use shared::bullet::Bullet;
+use shared::grenade::Grenade;
+use shared::thing::{Thing, ThingKind, ThingParams};
+use shared::spark::Spark;
+use shared::kit::{self, KitKind};
+use shared::flag::{self, Flag};
+use shared::events::GameEvent;
+use shared::calc;
+use shared::bullet;
+use shared::emitter::EmitterItem;
+use shared::gamemode::{GameMode, ModeContext, Scoreboard, Winner};
+
+pub const MAX_SOLDIERS: usize = 32;
+
+/// Things closer than this to a soldier's skeleton get picked up.
+const PICKUP_DISTANCE: f32 = 5.0;
+/// Flags closer than this to a soldier's skeleton are touched by it.
+const FLAG_TOUCH_DISTANCE: f32 = 10.0;
+/// Ticks between the end of a round and the start of the next one.
+const ROUND_RESTART_DELAY: i32 = 10 * 60;
+/// How far soldiers can see each other in realistic mode.
+const SIGHT_DISTANCE: f32 = 500.0;
+const HEAD: usize = 12;
+
+/// The whole simulation: the map and everything on it. It only changes in `step`, which given
+/// the same inputs always plays out the same way, with or without anyone watching.
+pub struct World {
+  pub state: MainState,
+  /// Ticks simulated so far.
+  pub tick: u32,
+  pub soldiers: Vec<Soldier>,
+  pub bullets: Vec<Bullet>,
+  pub grenades: Vec<Grenade>,
+  pub things: Vec<Thing>,
+  pub sparks: Vec<Spark>,
+  /// Kits waiting to (re)appear, with the ticks left until they do.
+  pub kit_respawns: Vec<(KitKind, i32)>,
+  pub flags: Vec<Flag>,
+  pub mode: Box<dyn GameMode>,
+  pub scores: Scoreboard,
+  /// Ticks left in the round, if it has a time limit.
+  pub time_left: i32,
+  /// Set once the round is over, until the next one starts.
+  pub winner: Option<Winner>,
+  restart_counter: i32,
+}
+
+impl World {
+  /// Sets up the map's kits and flags. Soldiers are added with `add_soldier`.
+  pub fn new(state: MainState) -> World {
+    let mut world = World {
+      mode: state.settings.game_mode.create(),
+      time_left: state.settings.time_limit,
+      state,
+      tick: 0,
+      soldiers: Vec::new(),
+      bullets: Vec::new(),
+      grenades: Vec::new(),
+      things: Vec::new(),
+      sparks: Vec::new(),
+      kit_respawns: Vec::new(),
+      flags: Vec::new(),
+      scores: Scoreboard::default(),
+      winner: None,
+      restart_counter: 0,
+    };
+
+    world.spawn_kits();
+    world.spawn_flags();
+    world
+  }
+
+  /// Picks the game mode's team with the fewest soldiers for a new one to join.
+  pub fn join_team(&self) -> u8 {
+    let teams = self.mode.teams();
+    let size = |team: u8| self.soldiers.iter().filter(|soldier| soldier.team == team).count();
+
+    teams.iter().cloned().min_by_key(|&team| size(team)).unwrap_or(TEAM_NONE)
+  }
+
+  /// Puts the game mode's flags at their bases, for the teams the map has a base for.
+  fn spawn_flags(&mut self) {
+    for &team in self.mode.flags() {
+      if let Some(base) = flag::base_position(&self.state.map, team) {
+        self.flags.push(Flag::new(team, base));
+      }
+    }
+  }
+
+  pub fn carried_flag(&self, num: usize) -> Option<&Flag> {
+    self.flags.iter().find(|flag| flag.holder == Some(num))
+  }
+
+  fn update_flags(&mut self) {
+    for flag in &mut self.flags {
+      let num = match flag.holder {
+        Some(num) => num,
+        None => continue,
+      };
+
+      let velocity = self.state.soldier_parts.velocity[num];
+
+      let velocity = match self.soldiers.iter().find(|soldier| soldier.num == num) {
+        Some(soldier) if !soldier.dead_meat && !soldier.control.flag_throw => {
+          flag.carry(&self.state, soldier);
+          continue;
+        }
+        // Thrown on purpose, rather than dropped by a dying or leaving soldier
+        Some(soldier) if !soldier.dead_meat => {
+          let position = flag.position();
+          let aim = Vector2::new(soldier.control.mouse_aim_x as f32, soldier.control.mouse_aim_y as f32);
+          velocity + calc::vec2normalize(aim - position, aim - position) * flag::FLAG_THROW_SPEED
+        }
+        _ => velocity,
+      };
+
+      flag.drop(velocity);
+      self.state.events.push(GameEvent::FlagDrop { team: flag.team, soldier: num });
+    }
+
+    for flag in self.flags.iter_mut().filter(|flag| flag.holder.is_none()) {
+      if flag.update(&mut self.state) {
+        self.state.events.push(GameEvent::FlagReturn { team: flag.team, soldier: None });
+      }
+    }
+
+    for i in 0..self.flags.len() {
+      if self.flags[i].holder.is_some() {
+        continue;
+      }
+
+      let touching = {
+        let flag = &self.flags[i];
+        let position = flag.position();
+
+        self.soldiers
+          .iter()
+          .filter(|soldier| {
+            !soldier.dead_meat && !soldier.half_dead && flag.thing.can_pick_up(soldier.num)
+              && bullet::skeleton_distance(soldier, position) < FLAG_TOUCH_DISTANCE
+          })
+          .map(|soldier| (soldier.num, soldier.team))
+          .collect::<Vec<_>>()
+      };
+
+      for (num, team) in touching {
+        let flag_team = self.flags[i].team;
+
+        if team == flag_team && flag_team != TEAM_NONE {
+          if !self.flags[i].at_base {
+            self.flags[i].return_to_base();
+            self.state.events.push(GameEvent::FlagReturn { team: flag_team, soldier: Some(num) });
+            break;
+          }
+        } else if self.carried_flag(num).is_none() && self.mode.can_grab_flag(flag_team, team) {
+          self.flags[i].grab(num);
+          self.state.events.push(GameEvent::FlagGrab { team: flag_team, soldier: num });
+          break;
+        }
+      }
+    }
+
+    // Carrying the enemy flag to your own flag while it's at its base scores
+    for i in 0..self.flags.len() {
+      let num = match self.flags[i].holder {
+        Some(num) => num,
+        None => continue,
+      };
+
+      let team = match self.soldier(num) {
+        Some(soldier) => soldier.team,
+        None => continue,
+      };
+
+      let captured = self.flags.iter().any(|own| {
+        own.team == team && own.at_base && own.holder.is_none()
+          && self.soldier(num).map_or(false, |soldier| {
+            bullet::skeleton_distance(soldier, own.position()) < FLAG_TOUCH_DISTANCE
+          })
+      });
+
+      if captured {
+        self.flags[i].return_to_base();
+        self.state.events.push(GameEvent::FlagCapture { team: self.flags[i].team, soldier: num });
+      }
+    }
+  }
+
+  /// Schedules the map's kits to appear on the next tick. Bonus kits are only placed if enabled in
+  /// the settings, one of each kind.
+  fn spawn_kits(&mut self) {
+    let map = &self.state.map;
+    let bonuses = iif!(self.state.settings.bonuses, 1, 0);
+
+    let counts = KitKind::values().iter().map(|&kind| match kind {
+      KitKind::Medikit => (kind, map.medikits as usize),
+      KitKind::Grenadekit => (kind, map.grenade_packs as usize),
+      _ => (kind, bonuses),
+    });
+
+    for (kind, count) in counts {
+      for _ in 0..count {
+        self.kit_respawns.push((kind, 0));
+      }
+    }
+  }
+
+  fn respawn_kits(&mut self) {
+    let mut ready = Vec::new();
+
+    for &mut (kind, ref mut counter) in &mut self.kit_respawns {
+      *counter -= 1;
+
+      if *counter <= 0 {
+        ready.push(kind);
+      }
+    }
+
+    self.kit_respawns.retain(|&(_, counter)| counter > 0);
+
+    for kind in ready {
+      // Maps without spawnpoints for a kit simply don't have it
+      if let Some(position) = kit::spawn_position(&self.state.map, kind, &mut self.state.rng) {
+        self.things.push(Thing::new(&ThingParams {
+          kind: ThingKind::Kit(kind),
+          owner: None,
+          position,
+          velocity: Vector2::zeros(),
+        }));
+      }
+    }
+  }
+
+  /// Spawns a new soldier and returns its id, which is also the index of its particle in
+  /// `MainState::soldier_parts`. Returns `None` if the world is full.
+  pub fn add_soldier(&mut self, team: u8) -> Option<usize> {
+    let num = (1..=MAX_SOLDIERS).find(|&num| self.soldier(num).is_none())?;
+    let position = self.spawn_position(team, num);
+    self.soldiers.push(Soldier::new(&mut self.state, num, team, position));
+    self.scores.player(num);
+    self.notify_spawn(num);
+    Some(num)
+  }
+
+  /// Spawns a soldier for the bot with the given profile, on whichever team needs it most.
+  pub fn add_bot(&mut self, profile: &BotProfile) -> Option<Bot> {
+    let team = self.join_team();
+    let num = self.add_soldier(team)?;
+
+    {
+      let World { ref state, ref mut soldiers, .. } = *self;
+
+      if let Some(soldier) = soldiers.iter_mut().find(|soldier| soldier.num == num) {
+        soldier.choose_weapons(state, profile.favourite_weapon, profile.secondary_weapon);
+        soldier.set_appearance(profile.appearance);
+      }
+    }
+
+    Some(Bot::new(num, profile.clone()))
+  }
+
+  /// Fills the world with as many bots as the settings ask for, going through the profiles in
+  /// order and making up generic ones if there are none.
+  pub fn add_bots(&mut self, profiles: &[BotProfile]) -> Vec<Bot> {
+    let mut bots = Vec::new();
+
+    for i in 0..self.state.settings.bots as usize {
+      let profile = match profiles.get(i % profiles.len().max(1)) {
+        Some(profile) => profile.clone(),
+        None => BotProfile::new(&format!("Bot {}", i + 1)),
+      };
+
+      if let Some(bot) = self.add_bot(&profile) {
+        bots.push(bot);
+      }
+    }
+
+    bots
+  }
+
+  /// The game mode only hears of the events of the tick being stepped, so spawns happening
+  /// anywhere else are passed on directly.
+  fn notify_spawn(&mut self, num: usize) {
+    let mut ctx = ModeContext {
+      soldiers: &self.soldiers,
+      things: &self.things,
+      flags: &self.flags,
+      scores: &mut self.scores,
+    };
+
+    self.mode.on_spawn(&mut ctx, num);
+  }
+
+  pub fn remove_soldier(&mut self, num: usize) {
+    if let Some(index) = self.soldiers.iter().position(|soldier| soldier.num == num) {
+      self.soldiers.remove(index);
+      self.scores.players.remove(&num);
+      self.state.soldier_parts.active[num] = false;
+    }
+  }
+
+  /// Chooses where soldier `num` of the given team should (re)spawn, keeping away from living
+  /// enemies. Without teams everybody else is an enemy.
+  pub fn spawn_position(&mut self, team: u8, num: usize) -> Vector2<f32> {
+    let enemies: Vec<Vector2<f32>> = self.soldiers
+      .iter()
+      .filter(|s| s.num != num && !s.dead_meat && (team == TEAM_NONE || s.team != team))
+      .map(|s| self.state.soldier_parts.pos[s.num])
+      .collect();
+
+    spawn::select_spawnpoint(&self.state.map.spawnpoints, team, &enemies, &mut self.state.rng)
+  }
+
+  pub fn soldier(&self, num: usize) -> Option<&Soldier> {
+    self.soldiers.iter().find(|soldier| soldier.num == num)
+  }
+
+  pub fn soldier_mut(&mut self, num: usize) -> Option<&mut Soldier> {
+    self.soldiers.iter_mut().find(|soldier| soldier.num == num)
+  }
+
+  fn pick_up_things(&mut self) {
+    for thing in self.things.iter_mut().filter(|thing| thing.active) {
+      let position = thing.position();
+
+      let nearby: Vec<usize> = self.soldiers
+        .iter()
+        .filter(|soldier| {
+          !soldier.dead_meat && !soldier.half_dead && thing.can_pick_up(soldier.num)
+            && bullet::skeleton_distance(soldier, position) < PICKUP_DISTANCE
+        })
+        .map(|soldier| soldier.num)
+        .collect();
+
+      // Things stay around for someone else if the first soldier can't take them
+      for num in nearby {
+        let soldier = match self.soldiers.iter_mut().find(|soldier| soldier.num == num) {
+          Some(soldier) => soldier,
+          None => continue,
+        };
+
+        let taken = match thing.kind {
+          ThingKind::Weapon(weapon) => soldier.pick_up_weapon(&mut self.state, weapon),
+          ThingKind::Kit(kind) => soldier.pick_up_kit(&mut self.state, kind),
+          ThingKind::Flag(_) => false,
+        };
+
+        if taken {
+          thing.active = false;
+          break;
+        }
+      }
+    }
+  }
+
+  /// Counts down `Soldier::visible` while no enemy has the soldier in sight. How that looks
+  /// depends on who's watching, so fading soldiers out is left to the renderer.
+  fn update_visibility(&mut self) {
+    let seen: Vec<bool> = self.soldiers
+      .iter()
+      .map(|soldier| {
+        let head = soldier.skeleton.pos[HEAD];
+
+        self.soldiers.iter().any(|other| {
+          let eye = other.skeleton.pos[HEAD];
+
+          other.num != soldier.num && !other.dead_meat
+            && (other.team == TEAM_NONE || other.team != soldier.team)
+            && calc::distance(eye, head) < SIGHT_DISTANCE
+            && bullet::line_of_sight(&self.state.map, other.team, eye, head)
+        })
+      })
+      .collect();
+
+    for (soldier, seen) in self.soldiers.iter_mut().zip(seen) {
+      soldier.visible = iif!(seen, VISIBLE_TIME, soldier.visible.saturating_sub(1));
+    }
+  }
+
+  /// Runs one simulation tick. Soldiers without an entry in `inputs` keep their aim and
+  /// release all buttons. The tick's events are left in `MainState::events` until the next step
+  /// clears them, so they don't pile up when nobody drains them.
+  pub fn step(&mut self, inputs: &HashMap<usize, InputFrame>) {
+    self.state.events.clear();
+
+    for soldier in &mut self.soldiers {
+      let input = match inputs.get(&soldier.num) {
+        Some(input) => *input,
+        None => InputFrame::new(soldier.control.mouse_aim_x, soldier.control.mouse_aim_y),
+      };
+
+      self.state.soldier_parts.do_eurler_timestep_for(soldier.num as i32);
+      soldier.update(&mut self.state, &input);
+    }
+
+    if self.state.settings.realistic_mode {
+      self.update_visibility();
+    }
+
+    let emitted: Vec<EmitterItem> = self.state.emitter.drain(..).collect();
+
+    for item in emitted {
+      match item {
+        EmitterItem::Bullet(params) => self.bullets.push(Bullet::new(&params)),
+        EmitterItem::Grenade(params) => self.grenades.push(Grenade::new(&params)),
+        EmitterItem::Thing(params) => self.things.push(Thing::new(&params)),
+        EmitterItem::Spark(params) => self.sparks.push(Spark::new(&params)),
+      }
+    }
+
+    for bullet in &mut self.bullets {
+      bullet.update(&mut self.state, &mut self.soldiers);
+    }
+
+    self.bullets.retain(|bullet| bullet.active);
+
+    for grenade in &mut self.grenades {
+      grenade.update(&mut self.state, &mut self.soldiers);
+    }
+
+    self.grenades.retain(|grenade| grenade.active);
+
+    for spark in &mut self.sparks {
+      spark.update();
+    }
+
+    self.sparks.retain(|spark| spark.active);
+
+    for thing in &mut self.things {
+      thing.update(&mut self.state);
+    }
+
+    self.pick_up_things();
+
+    for thing in self.things.iter().filter(|thing| !thing.active) {
+      if let ThingKind::Kit(kind) = thing.kind {
+        self.kit_respawns.push((kind, self.state.settings.kit_respawn_time));
+      }
+    }
+
+    self.things.retain(|thing| thing.active);
+    self.respawn_kits();
+    self.update_flags();
+
+    let mut respawning = Vec::new();
+
+    for soldier in &mut self.soldiers {
+      if soldier.dead_meat {
+        soldier.respawn_counter -= 1;
+
+        if soldier.respawn_counter <= 0 {
+          respawning.push((soldier.num, soldier.team));
+        }
+      }
+    }
+
+    for (num, team) in respawning {
+      let position = self.spawn_position(team, num);
+
+      if let Some(soldier) = self.soldiers.iter_mut().find(|soldier| soldier.num == num) {
+        soldier.respawn(&mut self.state, position);
+      }
+    }
+
+    let events: Vec<GameEvent> = self.state.events.clone();
+    self.update_game_mode(&events);
+    self.tick += 1;
+  }
+
+  /// Feeds the tick's events to the game mode and ends the round once it has a winner. A new
+  /// round starts a while after that.
+  fn update_game_mode(&mut self, events: &[GameEvent]) {
+    if let Some(winner) = self.winner {
+      // The winners cheer on the tick after the round ends
+      if self.restart_counter == ROUND_RESTART_DELAY {
+        self.cheer(winner);
+      }
+
+      self.restart_counter -= 1;
+
+      if self.restart_counter <= 0 {
+        self.restart_round();
+      }
+
+      return;
+    }
+
+    let mut ctx = ModeContext {
+      soldiers: &self.soldiers,
+      things: &self.things,
+      flags: &self.flags,
+      scores: &mut self.scores,
+    };
+
+    for event in events {
+      match *event {
+        GameEvent::Kill { killer, victim, .. } => {
+          ctx.scores.player(victim).deaths += 1;
+
+          if let Some(killer) = killer.filter(|&killer| killer != victim) {
+            ctx.scores.player(killer).kills += 1;
+            self.mode.on_kill(&mut ctx, killer, victim);
+          }
+        }
+        GameEvent::Respawn { soldier } => self.mode.on_spawn(&mut ctx, soldier),
+        GameEvent::FlagGrab { .. }
+        | GameEvent::FlagDrop { .. }
+        | GameEvent::FlagReturn { .. }
+        | GameEvent::FlagCapture { .. } => self.mode.on_flag(&mut ctx, event),
+        _ => (),
+      }
+    }
+
+    self.mode.tick(&mut self.state, &mut ctx);
+
+    if self.state.settings.time_limit > 0 {
+      self.time_left -= 1;
+    }
+
+    let time_up = self.state.settings.time_limit > 0 && self.time_left <= 0;
+
+    if let Some(winner) = self.mode.winner(ctx.scores, &self.state.settings, time_up) {
+      self.mode.on_round_end(&mut ctx, winner);
+      self.winner = Some(winner);
+      self.restart_counter = ROUND_RESTART_DELAY;
+      self.state.events.push(GameEvent::RoundEnd { winner });
+    }
+  }
+
+  fn cheer(&mut self, winner: Winner) {
+    for soldier in self.soldiers.iter_mut().filter(|soldier| !soldier.dead_meat) {
+      let won = match winner {
+        Winner::Soldier(num) => soldier.num == num,
+        Winner::Team(team) => soldier.team == team,
+        Winner::Draw => false,
+      };
+
+      if won {
+        soldier.body_apply_animation(self.state.anims.victory.clone(), 1);
+      }
+    }
+  }
+
+  /// Clears the scores and puts everybody and every flag back at their spawns.
+  pub fn restart_round(&mut self) {
+    self.winner = None;
+    self.time_left = self.state.settings.time_limit;
+    self.scores.reset();
+    self.bullets.clear();
+    self.grenades.clear();
+
+    // Kits start over from the map's spawnpoints, taken or not
+    self.things.retain(|thing| match thing.kind {
+      ThingKind::Kit(_) => false,
+      _ => true,
+    });
+    self.kit_respawns.clear();
+    self.spawn_kits();
+
+    for flag in &mut self.flags {
+      flag.return_to_base();
+    }
+
+    let soldiers: Vec<(usize, u8)> = self.soldiers.iter().map(|s| (s.num, s.team)).collect();
+
+    for (num, team) in soldiers {
+      let position = self.spawn_position(team, num);
+
+      if let Some(soldier) = self.soldiers.iter_mut().find(|soldier| soldier.num == num) {
+        soldier.respawn(&mut self.state, position);
+      }
+
+      self.notify_spawn(num);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use shared::control::Action;
+  use shared::mapfile::MapFile;
+  use shared::settings::GameSettings;
+
+  // Like the game, these need Soldat's assets in assets/, which aren't part of the repository.
+  // Run them with `cargo test -- --ignored` once they're copied in.
+  fn world(seed: u32) -> World {
+    let mut settings = GameSettings::default();
+    settings.seed = seed;
+
+    let map = MapFile::load_map_file("ctf_Ash.pms");
+    let mut world = World::new(MainState::new(map, settings));
+
+    for _ in 0..4 {
+      let team = world.join_team();
+      world.add_soldier(team);
+    }
+
+    world
+  }
+
+  /// Half of the soldiers run left and half right, shooting in bursts at a sweeping aim.
+  fn inputs(world: &World) -> HashMap<usize, InputFrame> {
+    world
+      .soldiers
+      .iter()
+      .map(|soldier| {
+        let mut input = InputFrame::new((world.tick * 7 % 2000) as i32 - 1000, 0);
+        input.set(Action::Left, soldier.num % 2 == 1);
+        input.set(Action::Right, soldier.num % 2 == 0);
+        input.set(Action::Fire, world.tick % 30 < 20);
+        input.set(Action::Jump, world.tick % 90 == 0);
+        input.set(Action::Grenade, world.tick % 300 < 30);
+        (soldier.num, input)
+      })
+      .collect()
+  }
+
+  fn fingerprint(world: &World) -> Vec<f32> {
+    let mut values = vec![world.tick as f32, world.state.events.len() as f32];
+
+    for soldier in &world.soldiers {
+      let pos = world.state.soldier_parts.pos[soldier.num];
+      values.extend(&[pos.x, pos.y, soldier.health]);
+    }
+
+    for bullet in &world.bullets {
+      values.extend(&[bullet.pos.x, bullet.pos.y]);
+    }
+
+    for grenade in &world.grenades {
+      values.extend(&[grenade.pos.x, grenade.pos.y]);
+    }
+
+    for thing in &world.things {
+      values.extend(&[thing.position().x, thing.position().y]);
+    }
+
+    values
+  }
+
+  #[test]
+  #[ignore]
+  fn same_seed_and_inputs_give_same_world() {
+    let (mut a, mut b) = (world(1234), world(1234));
+
+    for _ in 0..3000 {
+      let frame = inputs(&a);
+      a.step(&frame);
+      b.step(&frame);
+      assert_eq!(
+        fingerprint(&a),
+        fingerprint(&b),
+        "worlds diverged on tick {}",
+        a.tick
+      );
+    }
+
+    assert_eq!(a.tick, 3000);
+  }
+
+  #[test]
+  #[ignore]
+  fn step_only_keeps_the_last_ticks_events() {
+    let mut world = world(1);
+
+    for _ in 0..1000 {
+      let frame = inputs(&world);
+      world.step(&frame);
+    }
+
+    world.state.events.push(GameEvent::Respawn { soldier: 1 });
+    world.step(&HashMap::new());
+
+    let stale = world.state.events.iter().any(|event| match *event {
+      GameEvent::Respawn { soldier } => soldier == 1,
+      _ => false,
+    });
+
+    assert!(!stale);
+  }
+}